@@ -1,3 +1,497 @@
+use std::fs::{self, Metadata};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::SystemTime;
+use chrono::{DateTime, Local};
+use clap::{value_parser, Arg, ArgAction, ArgMatches, Command};
+use streaker::{Frames, Scan, Streak, Token, Walk};
+use crate::output::{Format, Record, SCHEMA_VERSION};
+
+mod output;
+
+/// `--format` option of the listing commands
+fn format_arg() -> Arg {
+    Arg::new("format")
+        .long("format")
+        .value_parser(Format::NAMES)
+        .default_value("text")
+        .help(format!("Write text, or entries as JSON, NDJSON or CSV with schema version {}", SCHEMA_VERSION))
+}
+
+fn cli() -> Command {
+    Command::new("streaker")
+        .version(env!("CARGO_PKG_VERSION"))
+        .about("Search for file sequences")
+        .subcommand_required(true)
+        .arg_required_else_help(true)
+        .subcommand(Command::new("ls")
+            .about("List directories with files collapsed into sequences")
+            .arg(Arg::new("long")
+                .short('l')
+                .long("long")
+                .action(ArgAction::SetTrue)
+                .help("Show frame count, missing frames, total size and newest modification time"))
+            .arg(Arg::new("all")
+                .short('a')
+                .long("all")
+                .action(ArgAction::SetTrue)
+                .help("Include entries starting with a dot"))
+            .arg(Arg::new("recursive")
+                .short('r')
+                .short_alias('R')
+                .long("recursive")
+                .action(ArgAction::SetTrue)
+                .help("List subdirectories too"))
+            .arg(format_arg())
+            .arg(Arg::new("paths")
+                .value_parser(value_parser!(PathBuf))
+                .num_args(0..)
+                .default_value(".")
+                .help("Directories or files to list")))
+        .subcommand(Command::new("missing")
+            .about("Report sequences with missing frames")
+            .arg(Arg::new("step")
+                .short('s')
+                .long("step")
+                .value_parser(value_parser!(u32).range(1..))
+                .help("Frames expected between renders, guessed per sequence by default"))
+            .arg(Arg::new("recursive")
+                .short('r')
+                .long("recursive")
+                .action(ArgAction::SetTrue)
+                .help("Search subdirectories too"))
+            .arg(format_arg())
+            .arg(Arg::new("paths")
+                .value_parser(value_parser!(PathBuf))
+                .num_args(0..)
+                .default_value(".")
+                .help("Directories to search")))
+}
+
 fn main() {
-    println!("Hello, world!");
+    let matches = cli().get_matches();
+    let code = match matches.subcommand() {
+        Some(("ls", matches)) => ls(matches),
+        Some(("missing", matches)) => missing(matches),
+        _ => unreachable!("subcommand is required"),
+    };
+    process::exit(code);
+}
+
+/// Scans of every directory requested, recursing when asked to
+fn scans(matches: &ArgMatches) -> impl Iterator<Item = io::Result<Scan>> + '_ {
+    let recursive = matches.get_flag("recursive");
+    matches
+        .get_many::<PathBuf>("paths")
+        .unwrap_or_default()
+        .flat_map(move |path| {
+            let walk = Walk::new(path);
+            if recursive { walk } else { walk.max_depth(0) }
+        })
+}
+
+/// Frames a sequence is missing, expected every `step` frames or guessed from the sequence
+fn absent(streak: &Streak, step: Option<u32>) -> Frames {
+    let frames = streak.frames();
+    let step = match step {
+        Some(step) => step.saturating_mul(10u32.pow(frames.precision())),
+        None => frames.min_step().unwrap_or(0),
+    };
+    frames.missing(step)
+}
+
+/// Describe the frames a sequence is missing
+fn report(streak: &Streak, missing: &Frames) -> String {
+    format!("{}: missing {} ({} of {})",
+            streak,
+            missing.token(),
+            missing.count(),
+            missing.count() + streak.frames().count())
+}
+
+/// Format chosen with `--format`
+fn format(matches: &ArgMatches) -> Format {
+    matches.get_one::<String>("format").map_or(Format::Text, |name| Format::from_name(name))
+}
+
+/// Write structured records to standard output, `2` if that failed
+fn emit(format: Format, records: &[Record]) -> i32 {
+    match output::write(&mut io::stdout().lock(), format, records) {
+        Ok(()) => 0,
+        Err(error) => {
+            eprintln!("streaker: {}", error);
+            2
+        }
+    }
+}
+
+/// Something listed by `ls`: a sequence, a file on its own or a subdirectory
+enum Entry {
+    Streak(Streak),
+    File(PathBuf),
+    Directory(PathBuf),
+}
+
+/// Columns of the long format, `None` where they do not apply
+#[derive(Debug, Default, PartialEq)]
+struct Stats {
+    frames: Option<usize>,
+    missing: Option<usize>,
+    size: Option<u64>,
+    modified: Option<SystemTime>,
+}
+
+impl Stats {
+
+    /// Read from disk, leaving out files that vanished or cannot be read
+    fn of(entry: &Entry) -> Stats {
+        let mut stats = Stats::default();
+        match entry {
+            Entry::Streak(streak) => {
+                let frames = streak.frames();
+                stats.frames = Some(frames.count());
+                stats.missing = Some(absent(streak, None).count());
+                for path in streak.paths() {
+                    if let Ok(metadata) = fs::metadata(path) {
+                        stats.add(&metadata);
+                    }
+                }
+            }
+            Entry::File(path) => {
+                if let Ok(metadata) = fs::metadata(path) {
+                    stats.add(&metadata);
+                }
+            }
+            Entry::Directory(path) => {
+                stats.modified = fs::metadata(path).and_then(|metadata| metadata.modified()).ok();
+            }
+        }
+        stats
+    }
+
+    /// Count a file towards the total size and newest modification time
+    fn add(&mut self, metadata: &Metadata) {
+        self.size = Some(self.size.unwrap_or(0) + metadata.len());
+        self.modified = self.modified.max(metadata.modified().ok());
+    }
+}
+
+/// Name of a file or directory without its parents
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map_or_else(|| path.display().to_string(), |name| name.to_string_lossy().into_owned())
+}
+
+/// Entries of a scanned directory by the name they are listed with, ordered by it.
+/// A sequence of a single frame is listed as the file it is.
+fn entries(scan: &Scan, hidden: bool) -> io::Result<Vec<(String, Entry)>> {
+    let mut entries = Vec::new();
+    for streak in scan.streaks() {
+        match streak.paths().next() {
+            Some(path) if streak.frames().count() == 1 => entries.push((file_name(&path), Entry::File(path))),
+            _ => entries.push((streak.file_name(), Entry::Streak(streak.clone()))),
+        }
+    }
+    for path in scan.singles().iter().chain(scan.ambiguous()) {
+        entries.push((file_name(path), Entry::File(path.clone())));
+    }
+    let annotate = |error: io::Error| io::Error::new(error.kind(), format!("{}: {}", scan.directory().display(), error));
+    for entry in fs::read_dir(scan.directory()).map_err(annotate)? {
+        let path = entry.map_err(annotate)?.path();
+        let name = file_name(&path);
+        if path.is_dir() && (hidden || !name.starts_with('.')) {
+            entries.push((format!("{}/", name), Entry::Directory(path)));
+        }
+    }
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(entries)
+}
+
+/// Size in bytes, eg. `512`, `1.5K` or `12G`
+fn human(bytes: u64) -> String {
+    let mut size = bytes as f64;
+    let mut unit = "";
+    for next in ["K", "M", "G", "T", "P", "E"].iter() {
+        if size < 1024.0 {
+            break;
+        }
+        size /= 1024.0;
+        unit = next;
+    }
+    match unit {
+        "" => bytes.to_string(),
+        _ if size < 10.0 => format!("{:.1}{}", size, unit),
+        _ => format!("{:.0}{}", size, unit),
+    }
+}
+
+/// Modification time in the local time zone, eg. `2024-03-01 14:05`
+fn timestamp(time: SystemTime) -> String {
+    DateTime::<Local>::from(time).format("%Y-%m-%d %H:%M").to_string()
+}
+
+/// Lines of the long format, a header then a row per entry with aligned columns,
+/// nothing at all when there are no entries
+fn long(rows: &[(String, Stats)]) -> Vec<String> {
+    if rows.is_empty() {
+        return Vec::new();
+    }
+    let dash = || "-".to_owned();
+    let mut table = vec![["FRAMES".to_owned(), "MISSING".to_owned(), "SIZE".to_owned(), "MODIFIED".to_owned()]];
+    for (_, stats) in rows {
+        table.push([
+            stats.frames.map_or_else(dash, |frames| frames.to_string()),
+            stats.missing.map_or_else(dash, |missing| missing.to_string()),
+            stats.size.map_or_else(dash, human),
+            stats.modified.map_or_else(dash, timestamp),
+        ]);
+    }
+    let mut widths = [0; 4];
+    for row in &table {
+        for (width, cell) in widths.iter_mut().zip(row.iter()) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let names = std::iter::once("NAME").chain(rows.iter().map(|(name, _)| name.as_str()));
+    table
+        .iter()
+        .zip(names)
+        .map(|(row, name)| format!("{:>w0$}  {:>w1$}  {:>w2$}  {:<w3$}  {}",
+                                   row[0], row[1], row[2], row[3], name,
+                                   w0 = widths[0], w1 = widths[1], w2 = widths[2], w3 = widths[3]))
+        .collect()
+}
+
+/// Print entries one per line, or in the long format
+fn print(entries: Vec<(String, Entry)>, long_format: bool) {
+    if !long_format {
+        for (name, _) in entries {
+            println!("{}", name);
+        }
+        return;
+    }
+    let rows: Vec<(String, Stats)> = entries
+        .into_iter()
+        .map(|(name, entry)| (name, Stats::of(&entry)))
+        .collect();
+    for line in long(&rows) {
+        println!("{}", line);
+    }
+}
+
+/// Entry of `directory` listed as `name`, in the shape of the schema
+fn record(directory: &Path, name: String, entry: &Entry, long_format: bool) -> Record {
+    let record = match entry {
+        Entry::Streak(streak) => Record::sequence(streak, absent(streak, None)),
+        Entry::File(_) => Record::file(directory, name),
+        Entry::Directory(_) => Record::directory(directory, name),
+    };
+    if !long_format {
+        return record;
+    }
+    let stats = Stats::of(entry);
+    record.with_stats(stats.size, stats.modified)
+}
+
+/// List directories like `ls`, with sequences in place of their files
+fn ls(matches: &ArgMatches) -> i32 {
+    let long_format = matches.get_flag("long");
+    let hidden = matches.get_flag("all");
+    let recursive = matches.get_flag("recursive");
+    let format = format(matches);
+    let paths: Vec<&PathBuf> = matches.get_many::<PathBuf>("paths").unwrap_or_default().collect();
+    let mut records = Vec::new();
+    let mut code = 0;
+
+    // Files named on the command line are listed first, as given
+    let (directories, files): (Vec<&PathBuf>, Vec<&PathBuf>) = paths.iter().partition(|path| path.is_dir());
+    let mut listed = Vec::new();
+    for path in files {
+        if let Err(error) = fs::symlink_metadata(path) {
+            eprintln!("streaker: {}: {}", path.display(), error);
+            code = 2;
+            continue;
+        }
+        let (name, entry) = (path.display().to_string(), Entry::File(path.to_path_buf()));
+        match format {
+            Format::Text => listed.push((name, entry)),
+            _ => records.push(record(path.parent().unwrap_or(path), name, &entry, long_format)),
+        }
+    }
+    let mut separate = !listed.is_empty();
+    if separate {
+        print(listed, long_format);
+    }
+
+    let headers = recursive || paths.len() > 1;
+    for path in directories {
+        let walk = Walk::new(path).hidden(hidden);
+        let walk = if recursive { walk } else { walk.max_depth(0) };
+        for scan in walk {
+            let entries = scan.and_then(|scan| {
+                let entries = entries(&scan, hidden)?;
+                Ok((scan, entries))
+            });
+            let (scan, entries) = match entries {
+                Ok(listing) => listing,
+                Err(error) => {
+                    eprintln!("streaker: {}", error);
+                    code = 2;
+                    continue;
+                }
+            };
+            if format != Format::Text {
+                for (name, entry) in entries {
+                    records.push(record(scan.directory(), name, &entry, long_format));
+                }
+                continue;
+            }
+            if separate {
+                println!();
+            }
+            if headers {
+                println!("{}:", scan.directory().display());
+            }
+            print(entries, long_format);
+            separate = true;
+        }
+    }
+    if format != Format::Text {
+        code = code.max(emit(format, &records));
+    }
+    code
+}
+
+/// Print incomplete sequences, exiting with `1` if there are any
+fn missing(matches: &ArgMatches) -> i32 {
+    let step = matches.get_one::<u32>("step").copied();
+    let format = format(matches);
+    let mut records = Vec::new();
+    let mut code = 0;
+    for scan in scans(matches) {
+        let scan = match scan {
+            Ok(scan) => scan,
+            Err(error) => {
+                eprintln!("streaker: {}", error);
+                code = 2;
+                continue;
+            }
+        };
+        for streak in scan.streaks() {
+            let missing = absent(streak, step);
+            if missing.count() == 0 {
+                continue;
+            }
+            code = code.max(1);
+            match format {
+                Format::Text => println!("{}", report(streak, &missing)),
+                _ => records.push(Record::sequence(streak, missing)),
+            }
+        }
+    }
+    if format != Format::Text {
+        code = code.max(emit(format, &records));
+    }
+    code
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::time::Duration;
+    use streaker::Padding;
+
+    fn streak(frames: &str) -> Streak {
+        Streak::new("render", ".", Padding::new(4), frames.parse().unwrap(), ".exr")
+    }
+
+    /// Fresh directory holding `files`, each as many bytes long as given
+    fn directory(name: &str, files: &[(&str, usize)]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("streaker-cli-{}-{}", process::id(), name));
+        let _ = fs::remove_dir_all(&path);
+        for (file, size) in files {
+            let file = path.join(file);
+            fs::create_dir_all(file.parent().unwrap()).unwrap();
+            fs::write(file, vec![0; *size]).unwrap();
+        }
+        path
+    }
+
+    #[test]
+    fn test_cli() {
+        cli().debug_assert();
+    }
+
+    #[test]
+    fn test_report() {
+        let report = |frames, step| {
+            let streak = streak(frames);
+            let missing = absent(&streak, step);
+            Some(report(&streak, &missing)).filter(|_| missing.count() > 0)
+        };
+        assert_eq!(report("1-3,7-8,10", None).unwrap(),
+                   "render.1-3,7,8,10#.exr: missing 4-6,9 (4 of 10)");
+        assert_eq!(report("1-9x2,13", None).unwrap(),
+                   "render.1-9x2,13#.exr: missing 11 (1 of 7)");
+        assert_eq!(report("1-9x2,13", Some(1)).unwrap(),
+                   "render.1-9x2,13#.exr: missing 2-8x2,10-12 (7 of 13)");
+        assert_eq!(report("1-100", None), None);
+        assert_eq!(report("1", None), None);
+    }
+
+    #[test]
+    fn test_entries() {
+        let path = directory("entries", &[
+            ("render.1001.exr", 10),
+            ("render.1002.exr", 20),
+            ("render.1004.exr", 30),
+            ("lone.0001.exr", 1),
+            ("notes.txt", 2),
+            (".hidden", 3),
+            ("plates/bg.1.dpx", 4),
+        ]);
+        let scan = Walk::new(&path).max_depth(0).next().unwrap().unwrap();
+        let names = |entries: Vec<(String, Entry)>| -> Vec<String> {
+            entries.into_iter().map(|(name, _)| name).collect()
+        };
+        assert_eq!(names(entries(&scan, false).unwrap()),
+                   vec!["lone.0001.exr", "notes.txt", "plates/", "render.1001,1002,1004#.exr"]);
+
+        let scan = Walk::new(&path).hidden(true).max_depth(0).next().unwrap().unwrap();
+        let listed = entries(&scan, true).unwrap();
+        assert_eq!(listed[0].0, ".hidden");
+        let (_, sequence) = listed.iter().find(|(name, _)| name.starts_with("render")).unwrap();
+        let stats = Stats::of(sequence);
+        assert_eq!((stats.frames, stats.missing, stats.size), (Some(3), Some(1), Some(60)));
+        assert!(stats.modified.is_some());
+        fs::remove_dir_all(path).unwrap();
+    }
+
+    #[test]
+    fn test_human() {
+        assert_eq!(human(0), "0");
+        assert_eq!(human(1023), "1023");
+        assert_eq!(human(1536), "1.5K");
+        assert_eq!(human(200 * 1024), "200K");
+        assert_eq!(human(3 << 30), "3.0G");
+        assert_eq!(human(u64::MAX), "16E");
+    }
+
+    #[test]
+    fn test_long() {
+        let rows = vec![
+            ("render.1-100#.exr".to_owned(), Stats { frames: Some(100), missing: Some(0), size: Some(1 << 20), modified: None }),
+            ("notes.txt".to_owned(), Stats { size: Some(12), ..Stats::default() }),
+        ];
+        assert_eq!(long(&rows), vec![
+            "FRAMES  MISSING  SIZE  MODIFIED  NAME",
+            "   100        0  1.0M  -         render.1-100#.exr",
+            "     -        -    12  -         notes.txt",
+        ]);
+        assert!(long(&[]).is_empty());
+        let time = SystemTime::UNIX_EPOCH + Duration::from_secs(86400 * 365);
+        assert_eq!(timestamp(time).len(), "1971-01-01 00:00".len());
+    }
 }
@@ -0,0 +1,382 @@
+//! Machine-readable output of the listing commands, `ls` and `missing`.
+//!
+//! `--format json` writes one document, `{"schema_version": 1, "entries": [...]}`,
+//! `--format ndjson` writes one entry per line and `--format csv` one row per
+//! entry below a header. Every entry carries `schema_version`, which goes up
+//! whenever a field is removed or changes meaning. Fields may be added
+//! without a new version, so parsers should ignore those they do not know.
+//!
+//! Fields of an entry, `null` (or an empty CSV cell) where they do not apply:
+//!
+//! - `kind`: `sequence`, `file` or `directory`
+//! - `directory`: directory holding the entry, as given on the command line
+//! - `name`: name as listed, eg. `render.1001-1100#.exr`
+//! - `prefix`, `separator`, `suffix`: parts of a sequence name around its
+//!   frame number, eg. `render`, `.` and `.exr`
+//! - `extension`: suffix after its first dot, eg. `exr`
+//! - `padding`: `{"width": 4, "notation": "streaker", "token": "#"}`, where
+//!   notation is one of `streaker`, `hash`, `printf`, `houdini`, `maya` or `udim`.
+//!   CSV has `padding_width` and `padding_notation` columns.
+//! - `frames`: `{"token": "1-3,5", "ranges": [{"start": 1, "stop": 3, "step": 1}, ...],
+//!   "count": 4, "first": 1, "last": 5}`, where ranges are the longest runs of constant
+//!   step read from the first frame. Subframes are decimal numbers, eg. `1.5`.
+//!   CSV has `frames`, `ranges`, `count`, `first` and `last` columns, with ranges
+//!   written `start:stop:step` and separated by spaces.
+//! - `missing`: frames absent from a sequence, shaped like `frames`.
+//!   CSV has `missing` and `missing_count` columns.
+//! - `size`: total size in bytes, only with `ls --long`
+//! - `modified`: newest modification time in RFC 3339 UTC, eg.
+//!   `2024-03-01T14:05:00Z`, only with `ls --long`
+
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::SystemTime;
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde_json::{json, Value};
+use streaker::{Frames, Notation, Padding, Range, Streak, Token};
+
+/// Version of the fields written, see the module documentation
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// How a listing command writes what it found
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Text,
+    Json,
+    Ndjson,
+    Csv,
+}
+
+impl Format {
+
+    /// Names accepted by `--format`
+    pub const NAMES: [&'static str; 4] = ["text", "json", "ndjson", "csv"];
+
+    /// Format named by `--format`, text for anything unknown
+    pub fn from_name(name: &str) -> Format {
+        match name {
+            "json" => Format::Json,
+            "ndjson" => Format::Ndjson,
+            "csv" => Format::Csv,
+            _ => Format::Text,
+        }
+    }
+}
+
+/// A listed entry, in the shape of the schema
+#[derive(Debug, Clone)]
+pub struct Record {
+    kind: &'static str,
+    directory: String,
+    name: String,
+    streak: Option<(Streak, Frames)>,
+    size: Option<u64>,
+    modified: Option<SystemTime>,
+}
+
+/// Columns of the CSV format, in order
+const COLUMNS: [&str; 19] = [
+    "schema_version", "kind", "directory", "name", "prefix", "separator", "suffix", "extension",
+    "padding_width", "padding_notation", "frames", "ranges", "count", "first", "last",
+    "missing", "missing_count", "size", "modified",
+];
+
+fn notation(notation: Notation) -> &'static str {
+    match notation {
+        Notation::Streaker => "streaker",
+        Notation::Hash => "hash",
+        Notation::Printf => "printf",
+        Notation::Houdini => "houdini",
+        Notation::Maya => "maya",
+        Notation::Udim => "udim",
+    }
+}
+
+/// Units of `10^-precision` frames as a JSON number, eg. `1001` or `1001.25`
+fn number(units: i64, precision: u32) -> Value {
+    match precision {
+        0 => json!(units),
+        _ => json!(units as f64 / 10f64.powi(precision as i32)),
+    }
+}
+
+/// Text of a JSON number, eg. `1001.25`
+fn text(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(text) => text.clone(),
+        value => value.to_string(),
+    }
+}
+
+fn frames_value(frames: &Frames) -> Value {
+    let precision = frames.precision();
+    let ranges: Vec<Value> = frames
+        .canonical_runs()
+        .iter()
+        .map(|range: &Range| {
+            // A lone frame has no step of its own, it is written as one whole frame
+            let step = match range.start() == range.stop() {
+                true => 10u32.pow(precision),
+                false => range.step(),
+            };
+            json!({
+                "start": number(range.start().into(), precision),
+                "stop": number(range.stop().into(), precision),
+                "step": number(step.into(), precision),
+            })
+        })
+        .collect();
+    json!({
+        "token": frames.token(),
+        "ranges": ranges,
+        "count": frames.count(),
+        "first": frames.first().map(|first| number(first.into(), precision)),
+        "last": frames.last().map(|last| number(last.into(), precision)),
+    })
+}
+
+fn padding_value(padding: &Padding) -> Value {
+    json!({
+        "width": padding.value(),
+        "notation": notation(padding.notation()),
+        "token": padding.token(),
+    })
+}
+
+impl Record {
+
+    fn new(kind: &'static str, directory: &Path, name: String) -> Self {
+        Record {
+            kind,
+            directory: directory.display().to_string(),
+            name,
+            streak: None,
+            size: None,
+            modified: None,
+        }
+    }
+
+    /// A sequence and the frames it is missing
+    pub fn sequence(streak: &Streak, missing: Frames) -> Self {
+        let mut record = Record::new("sequence", streak.directory(), streak.file_name());
+        record.streak = Some((streak.clone(), missing));
+        record
+    }
+
+    /// A file listed as `name`, outside of any sequence
+    pub fn file(directory: &Path, name: String) -> Self {
+        Record::new("file", directory, name)
+    }
+
+    /// A subdirectory listed as `name`
+    pub fn directory(directory: &Path, name: String) -> Self {
+        Record::new("directory", directory, name)
+    }
+
+    /// Same entry, with its total size and newest modification time
+    pub fn with_stats(mut self, size: Option<u64>, modified: Option<SystemTime>) -> Self {
+        self.size = size;
+        self.modified = modified;
+        self
+    }
+
+    /// Entry as a JSON object
+    pub fn to_json(&self) -> Value {
+        let streak = self.streak.as_ref().map(|(streak, _)| streak);
+        let missing = self.streak.as_ref().map(|(_, missing)| missing);
+        json!({
+            "schema_version": SCHEMA_VERSION,
+            "kind": self.kind,
+            "directory": self.directory,
+            "name": self.name,
+            "prefix": streak.map(Streak::prefix),
+            "separator": streak.map(Streak::separator),
+            "suffix": streak.map(Streak::suffix),
+            "extension": streak.and_then(Streak::extension),
+            "padding": streak.map(|streak| padding_value(streak.padding())),
+            "frames": streak.map(|streak| frames_value(streak.frames())),
+            "missing": missing.map(frames_value),
+            "size": self.size,
+            "modified": self.modified.map(|modified| {
+                DateTime::<Utc>::from(modified).to_rfc3339_opts(SecondsFormat::Secs, true)
+            }),
+        })
+    }
+
+    /// Cells of a CSV row, in the order of `COLUMNS`
+    fn to_row(&self) -> Vec<String> {
+        let json = self.to_json();
+        let field = |path: &[&str]| {
+            let value = path.iter().try_fold(&json, |value, key| value.get(key));
+            value.map(text).unwrap_or_default()
+        };
+        let ranges = json["frames"]["ranges"]
+            .as_array()
+            .map(|ranges| {
+                ranges.iter()
+                    .map(|range| format!("{}:{}:{}", text(&range["start"]), text(&range["stop"]), text(&range["step"])))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .unwrap_or_default();
+        vec![
+            field(&["schema_version"]),
+            field(&["kind"]),
+            field(&["directory"]),
+            field(&["name"]),
+            field(&["prefix"]),
+            field(&["separator"]),
+            field(&["suffix"]),
+            field(&["extension"]),
+            field(&["padding", "width"]),
+            field(&["padding", "notation"]),
+            field(&["frames", "token"]),
+            ranges,
+            field(&["frames", "count"]),
+            field(&["frames", "first"]),
+            field(&["frames", "last"]),
+            field(&["missing", "token"]),
+            field(&["missing", "count"]),
+            field(&["size"]),
+            field(&["modified"]),
+        ]
+    }
+}
+
+/// Quote a CSV cell when it holds a comma, quote or line break
+fn quote(cell: &str) -> String {
+    if cell.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", cell.replace('"', "\"\""))
+    } else {
+        cell.to_owned()
+    }
+}
+
+/// Write `records` in a structured `format`, nothing for `Format::Text`
+pub fn write<W: Write>(out: &mut W, format: Format, records: &[Record]) -> io::Result<()> {
+    match format {
+        Format::Text => {}
+        Format::Json => {
+            let entries: Vec<Value> = records.iter().map(Record::to_json).collect();
+            let document = json!({ "schema_version": SCHEMA_VERSION, "entries": entries });
+            serde_json::to_writer_pretty(&mut *out, &document)?;
+            writeln!(out)?;
+        }
+        Format::Ndjson => {
+            for record in records {
+                serde_json::to_writer(&mut *out, &record.to_json())?;
+                writeln!(out)?;
+            }
+        }
+        Format::Csv => {
+            writeln!(out, "{}", COLUMNS.join(","))?;
+            for record in records {
+                let row: Vec<String> = record.to_row().iter().map(|cell| quote(cell)).collect();
+                writeln!(out, "{}", row.join(","))?;
+            }
+        }
+    }
+    out.flush()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn streak(frames: &str) -> Streak {
+        Streak::new("render", ".", Padding::new(4), frames.parse().unwrap(), ".exr").with_directory("/shots")
+    }
+
+    fn written(format: Format, records: &[Record]) -> String {
+        let mut out = Vec::new();
+        write(&mut out, format, records).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_json() {
+        let record = Record::sequence(&streak("1-3,5"), "4".parse().unwrap());
+        assert_eq!(record.to_json(), json!({
+            "schema_version": 1,
+            "kind": "sequence",
+            "directory": "/shots",
+            "name": "render.1-3,5#.exr",
+            "prefix": "render",
+            "separator": ".",
+            "suffix": ".exr",
+            "extension": "exr",
+            "padding": { "width": 4, "notation": "streaker", "token": "#" },
+            "frames": {
+                "token": "1-3,5",
+                "ranges": [{ "start": 1, "stop": 3, "step": 1 }, { "start": 5, "stop": 5, "step": 1 }],
+                "count": 4,
+                "first": 1,
+                "last": 5,
+            },
+            "missing": {
+                "token": "4",
+                "ranges": [{ "start": 4, "stop": 4, "step": 1 }],
+                "count": 1,
+                "first": 4,
+                "last": 4,
+            },
+            "size": null,
+            "modified": null,
+        }));
+        let file = Record::file(Path::new("/shots"), "notes.txt".to_owned())
+            .with_stats(Some(12), Some(SystemTime::UNIX_EPOCH));
+        let json = file.to_json();
+        assert_eq!((&json["kind"], &json["frames"], &json["size"]), (&json!("file"), &Value::Null, &json!(12)));
+        assert_eq!(json["modified"], "1970-01-01T00:00:00Z");
+    }
+
+    #[test]
+    fn test_subframes() {
+        let record = Record::sequence(&streak("1-2x0.25"), Frames::new(&[]));
+        let frames = &record.to_json()["frames"];
+        assert_eq!(frames["ranges"], json!([{ "start": 1.0, "stop": 2.0, "step": 0.25 }]));
+        assert_eq!(frames["token"], "1-2x0.25");
+    }
+
+    #[test]
+    fn test_ranges() {
+        let record = Record::sequence(&streak("1001,1002,1004,1005"), Frames::new(&[]));
+        let frames = &record.to_json()["frames"];
+        assert_eq!(frames["token"], "1001,1002,1004,1005");
+        assert_eq!(frames["ranges"], json!([
+            { "start": 1001, "stop": 1002, "step": 1 },
+            { "start": 1004, "stop": 1005, "step": 1 },
+        ]));
+        let record = Record::sequence(&streak("1-10,12,14,16"), Frames::new(&[]));
+        let frames = &record.to_json()["frames"];
+        assert_eq!(frames["token"], "1-9,10-16x2");
+        assert_eq!(frames["ranges"], json!([
+            { "start": 1, "stop": 10, "step": 1 },
+            { "start": 12, "stop": 16, "step": 2 },
+        ]));
+    }
+
+    #[test]
+    fn test_write() {
+        let records = vec![
+            Record::sequence(&streak("1-10x2"), Frames::new(&[])),
+            Record::directory(Path::new("/shots"), "plates, old/".to_owned()),
+        ];
+        let json: Value = serde_json::from_str(&written(Format::Json, &records)).unwrap();
+        assert_eq!(json["schema_version"], SCHEMA_VERSION);
+        assert_eq!(json["entries"].as_array().unwrap().len(), 2);
+
+        let ndjson = written(Format::Ndjson, &records);
+        let lines: Vec<Value> = ndjson.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(lines[1]["kind"], "directory");
+
+        let csv = written(Format::Csv, &records);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], COLUMNS.join(","));
+        assert_eq!(lines[1], "1,sequence,/shots,render.1-9x2#.exr,render,.,.exr,exr,4,streaker,1-9x2,1:9:2,5,1,9,,0,,");
+        assert_eq!(lines[2], "1,directory,/shots,\"plates, old/\",,,,,,,,,,,,,,,");
+        assert_eq!(written(Format::Text, &records), "");
+    }
+}
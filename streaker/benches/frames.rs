@@ -0,0 +1,63 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use streaker::{Frame, Frames, Token};
+
+/// Sequences of growing length: every frame, every other frame, and every frame with holes
+fn sequences() -> Vec<(String, Frames)> {
+  let mut sequences = Vec::new();
+  for &length in [1_000, 100_000, 10_000_000].iter() {
+    let stop = length as Frame;
+    sequences.push((format!("fill/{}", length), format!("1-{}", stop).parse().unwrap()));
+    sequences.push((format!("step/{}", length), format!("1-{}x2", stop).parse().unwrap()));
+    let holes: Frames = format!("1-{}x97", stop).parse().unwrap();
+    sequences.push((format!("holes/{}", length), format!("1-{}", stop).parse::<Frames>().unwrap() - holes));
+  }
+  sequences
+}
+
+fn properties(c: &mut Criterion) {
+  let mut group = c.benchmark_group("properties");
+  for (name, frames) in sequences().iter() {
+    group.bench_with_input(BenchmarkId::new("first_last", name), frames, |b, frames| {
+      b.iter(|| (black_box(frames).first(), black_box(frames).last()))
+    });
+    group.bench_with_input(BenchmarkId::new("skip", name), frames, |b, frames| {
+      b.iter(|| black_box(frames).skip())
+    });
+    group.bench_with_input(BenchmarkId::new("count", name), frames, |b, frames| {
+      b.iter(|| black_box(frames).count())
+    });
+    group.bench_with_input(BenchmarkId::new("body", name), frames, |b, frames| {
+      b.iter(|| black_box(frames).body())
+    });
+  }
+  group.finish();
+}
+
+fn token(c: &mut Criterion) {
+  let mut group = c.benchmark_group("token");
+  group.sample_size(10);
+  for (name, frames) in sequences().iter() {
+    group.bench_with_input(BenchmarkId::from_parameter(name), frames, |b, frames| {
+      b.iter(|| black_box(frames).token())
+    });
+  }
+  group.finish();
+}
+
+fn edit(c: &mut Criterion) {
+  let mut group = c.benchmark_group("edit");
+  for (name, frames) in sequences().iter() {
+    let middle = frames.first().unwrap() + (frames.last().unwrap() - frames.first().unwrap()) / 2;
+    group.bench_with_input(BenchmarkId::new("insert_token", name), frames, |b, frames| {
+      b.iter(|| {
+        let mut frames = frames.clone();
+        frames.insert(black_box(middle) | 1);
+        frames.token()
+      })
+    });
+  }
+  group.finish();
+}
+
+criterion_group!(benches, properties, token, edit);
+criterion_main!(benches);
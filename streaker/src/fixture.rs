@@ -0,0 +1,34 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+/// Temporary directory of empty files, removed when dropped
+pub struct Fixture {
+  path: PathBuf,
+}
+
+impl Fixture {
+
+  /// Create `files` relative to a fresh directory unique to `name`
+  pub fn new(name: &str, files: &[&str]) -> Self {
+    let path = std::env::temp_dir().join(format!("streaker-{}-{}", process::id(), name));
+    let _ = fs::remove_dir_all(&path);
+    fs::create_dir_all(&path).unwrap();
+    for file in files {
+      let file = path.join(file);
+      fs::create_dir_all(file.parent().unwrap()).unwrap();
+      fs::write(file, b"").unwrap();
+    }
+    Fixture { path }
+  }
+
+  pub fn path(&self) -> &Path {
+    &self.path
+  }
+}
+
+impl Drop for Fixture {
+  fn drop(&mut self) {
+    let _ = fs::remove_dir_all(&self.path);
+  }
+}
@@ -1,10 +1,28 @@
-use std::collections::BTreeSet;
+use std::borrow::Cow;
+use std::convert::TryFrom;
+use std::ops::{BitAnd, BitOr, BitXor, Sub};
+use std::str::FromStr;
+use crate::range::{decimal, FramesError, Range, Stride};
+#[cfg(feature = "serde")]
+use crate::range::fixed;
+use crate::runs::{FrameIter, Operation, Run, Runs};
 use crate::traits::Token;
 
-type FrameNumbers = BTreeSet<u32>;
+/// A frame number, negative for pre-roll
+pub type Frame = i32;
 
+/// A set of frame numbers.
+///
+/// Subframes, eg. `1001.25`, are stored in units of `10^-precision` frames
+/// so `1001.25` is `100125` at a precision of 2. Integer frames have a
+/// precision of 0, where a unit is a frame.
+///
+/// Frames are kept as runs of constant step rather than one by one, so
+/// `1-1000000` costs as little as `1-10`.
+#[derive(Debug, Clone, Eq)]
 pub struct Frames {
-  frames: FrameNumbers,
+  runs: Runs,
+  precision: u32,
 }
 
 #[derive(Debug, PartialEq)]
@@ -19,104 +37,623 @@ pub enum Body {
 
 impl Token for Frames {
   fn token(&self) -> String {
-    let frame_token = match self.body() {
-      Body::Contiguous => {
-        if self.count() == 0 {
-          String::new()
-        } else if self.count() == 1 {
-          self.first().unwrap().to_string()
-        } else {
-          format!("{}-{}",
-                  self.first().unwrap().to_string(),
-                  self.last().unwrap().to_string())
-        }
-      }
-      Body::Indirect => {
-        let s: Vec<String> = self.frames.iter().map(|f| f.to_string()).collect();
+    match (self.first(), self.last(), self.skip()) {
+      (Some(first), Some(last), Some(skip)) =>
+        Range::new(first, last, skip, Stride::Fill).with_precision(self.precision).to_string(),
+      (None, _, _) => String::new(),
+      _ => {
+        let s: Vec<String> = self.runs().iter().map(|r| r.to_string()).collect();
         s.join(",")
       }
+    }
+  }
+}
+
+impl FromStr for Frames {
+  type Err = FramesError;
+
+  /// Parse a comma separated frame range expression, eg. `1,3,5-9,10-20x2`.
+  /// Subframes take the precision of the most precise number, eg. `1-2x0.25`.
+  /// An empty or blank expression is the empty set, whose token is empty.
+  /// At most `Frames::DEFAULT_BUDGET` frames may be described.
+  fn from_str(pattern: &str) -> Result<Self, Self::Err> {
+    Frames::parse_with_budget(pattern, Frames::DEFAULT_BUDGET)
+  }
+}
+
+impl PartialEq for Frames {
+
+  /// Frames are equal when they hold the same frames, whatever their precision
+  fn eq(&self, other: &Self) -> bool {
+    let (coarse, fine) = match self.precision <= other.precision {
+      true => (self, other),
+      false => (other, self),
     };
-    frame_token
+    if coarse.precision == fine.precision {
+      return coarse.runs == fine.runs;
+    }
+    coarse.rescale(fine.precision).is_some_and(|coarse| coarse.runs == fine.runs)
+  }
+}
+
+/// Implement a set operator, for both owned and borrowed frames, with a method of `Frames`
+/// Operators call the named method, and panic like it when a frame
+/// overflows at the finer precision of the two sets
+macro_rules! operator {
+  ($operator:ident, $function:ident, $method:ident) => {
+    #[doc = concat!("Same as `Frames::", stringify!($method), "`, which panics if a frame overflows")]
+    impl $operator<&Frames> for &Frames {
+      type Output = Frames;
+
+      fn $function(self, other: &Frames) -> Frames {
+        self.$method(other)
+      }
+    }
+
+    #[doc = concat!("Same as `Frames::", stringify!($method), "`, which panics if a frame overflows")]
+    impl $operator for Frames {
+      type Output = Frames;
+
+      fn $function(self, other: Frames) -> Frames {
+        self.$method(&other)
+      }
+    }
+  };
+}
+
+operator!(BitOr, bitor, union);
+operator!(BitAnd, bitand, intersection);
+operator!(Sub, sub, difference);
+operator!(BitXor, bitxor, symmetric_difference);
+
+/// Frames serialize as their token, eg. `"1-10,12"`. When no number of the
+/// token has every decimal place, the first frame is written with them, eg.
+/// `"1001.50-1002x0.5"`, so subframes parse back at the same precision and
+/// name the same files.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Frames {
+  fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    let token = self.token();
+    let places = token
+      .split(|c: char| !c.is_ascii_digit() && c != '.')
+      .filter_map(|number| number.split_once('.'))
+      .map(|(_, fraction)| fraction.len() as u32)
+      .max()
+      .unwrap_or(0);
+    match self.first() {
+      Some(first) if places < self.precision => {
+        let written = decimal(first.into(), self.precision).len();
+        let token = format!("{}{}", fixed(first.into(), self.precision), &token[written..]);
+        serializer.serialize_str(&token)
+      }
+      _ => serializer.serialize_str(&token),
+    }
+  }
+}
+
+/// Frames deserialize from a token, eg. `"1-10,12"`, or a list of frames,
+/// eg. `[1, 2, 3]`, so need a self-describing format such as JSON
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Frames {
+  fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    use serde::de::{Error, SeqAccess, Visitor};
+
+    struct FramesVisitor;
+
+    impl<'de> Visitor<'de> for FramesVisitor {
+      type Value = Frames;
+
+      fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("frames such as \"1-10,12\" or a list of frames")
+      }
+
+      fn visit_str<E: Error>(self, token: &str) -> Result<Frames, E> {
+        token.parse().map_err(E::custom)
+      }
+
+      fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Frames, A::Error> {
+        let mut frames = Vec::new();
+        while let Some(frame) = seq.next_element::<Frame>()? {
+          frames.push(frame);
+        }
+        Ok(Frames::new(&frames))
+      }
+    }
+
+    deserializer.deserialize_any(FramesVisitor)
+  }
+}
+
+/// Append the frames of an inverse range, eg. `1-10y3`, a run per stretch
+/// between skipped frames. There are fewer stretches than frames, so this
+/// is bounded by `Range::count` however wide the range, eg. `0-2000000000y1`.
+fn push_inverse(runs: &mut Runs, range: &Range) {
+  let (start, stop, step) = (range.start() as i64, range.stop() as i64, range.step() as i64);
+  let mut push = |run: Option<Run>| run.into_iter().for_each(|run| runs.push(run));
+  match step {
+    1 => {}
+    2 => push(Run::new(start + 1, stop, 2)),
+    _ => for skipped in (start..=stop).step_by(step as usize) {
+      push(Run::new(skipped + 1, (skipped + step - 1).min(stop), 1));
+    },
+  }
+}
+
+impl TryFrom<&str> for Frames {
+  type Error = FramesError;
+
+  fn try_from(pattern: &str) -> Result<Self, Self::Error> {
+    pattern.parse()
   }
 }
 
 impl Frames {
 
-  pub fn new(frames: &[u32]) -> Self {
+  /// Most frames an expression parsed with `from_str` may describe
+  pub const DEFAULT_BUDGET: u64 = 10_000_000;
+
+  /// Most decimal places of a subframe, so that a whole frame of units fits in a step
+  pub const MAX_PRECISION: u32 = 9;
+
+  pub fn new(frames: &[Frame]) -> Self {
+    Frames::with_precision(frames, 0)
+  }
+
+  /// Subframes, given in units of `10^-precision` frames
+  ///
+  /// # Panics
+  ///
+  /// If `precision` is more than `Frames::MAX_PRECISION`.
+  pub fn with_precision(units: &[Frame], precision: u32) -> Self {
+    assert!(precision <= Frames::MAX_PRECISION, "precision of {} is too fine", precision);
     Frames {
-      frames: frames
-        .iter()
-        .map(|t| t.clone())
-        .collect()
+      runs: Runs::from_frames(units),
+      precision,
     }
   }
 
-  pub fn skip(&self) -> Option<u32> {
-    let skip = {
-      if self.count() == 0 {
-        None
-      } else if self.count() == 1 {
-        Some(1)
-      } else {
-        let mut iter = self.frames.iter();
-        let first = iter.next().unwrap();
-        let next = iter.next().unwrap();
-        let mut skip = next - first;
-        let mut prev = next;
-        for val in iter {
-          let next_skip = val - prev;
-          if next_skip != skip {
-            return None;
-          }
-          skip = next_skip;
-          prev = val;
-        }
-        Some(skip)
+  /// Parse frames like `from_str`, failing with `FramesError::TooManyFrames`
+  /// once they describe more than `budget` frames, eg. `0-2000000000`
+  pub fn parse_with_budget(pattern: &str, budget: u64) -> Result<Self, FramesError> {
+    if pattern.trim().is_empty() {
+      return Ok(Frames::new(&[]));
+    }
+    let mut ranges = Vec::new();
+    let mut offset = 0;
+    for chunk in pattern.split(',') {
+      let trimmed = chunk.trim_start();
+      let position = offset + chunk.len() - trimmed.len();
+      ranges.push((Range::parse(trimmed.trim_end(), position)?, position));
+      offset += chunk.len() + 1;
+    }
+
+    let precision = ranges.iter().map(|(range, _)| range.precision()).max().unwrap_or(0);
+    let mut chunks = Runs::default();
+    let mut pieces = Vec::new();
+    let mut described = 0;
+    for (range, position) in ranges {
+      let range = range.rescale(precision).ok_or(FramesError::Overflow { position })?;
+      described += range.count();
+      if described > budget {
+        return Err(FramesError::TooManyFrames { position, budget });
+      }
+      let mut chunk = Runs::default();
+      match range.stride() {
+        Stride::Fill => chunk.push(Run::new(range.start() as i64, range.stop() as i64, range.step())
+          .expect("parsed ranges are not reversed")),
+        Stride::Inverse => push_inverse(&mut chunk, &range),
+      }
+
+      // Chunks written in order are appended, others are sorted and merged once at the end
+      match (chunks.last(), chunk.first()) {
+        (Some(last), Some(first)) if last >= first || !pieces.is_empty() => pieces.extend(chunk.runs()),
+        _ => chunk.runs().iter().for_each(|run| chunks.push(*run)),
+      }
+    }
+    let runs = match pieces.is_empty() {
+      true => chunks,
+      false => {
+        pieces.extend(chunks.runs());
+        Runs::from_unsorted(pieces)
       }
     };
-    skip
+    Ok(Frames { runs, precision })
+  }
+
+  /// Number of decimal places, `0` for integer frames
+  pub fn precision(&self) -> u32 {
+    self.precision
+  }
+
+  /// Same frames with `precision` decimal places, `None` if that is fewer
+  /// places than they have or a frame no longer fits
+  pub fn rescale(&self, precision: u32) -> Option<Self> {
+    if precision > Frames::MAX_PRECISION {
+      return None;
+    }
+    let scale = 10u32.checked_pow(precision.checked_sub(self.precision)?)?;
+    let mut runs = Runs::default();
+    for run in self.runs.runs() {
+      runs.push(Run {
+        start: run.start.checked_mul(Frame::try_from(scale).ok()?)?,
+        stop: run.stop.checked_mul(Frame::try_from(scale).ok()?)?,
+        step: if run.start == run.stop { 1 } else { run.step.checked_mul(scale)? },
+      });
+    }
+    Some(Frames { runs, precision })
   }
 
-  pub fn first(&self) -> Option<u32> {
-    let first = {
-      if self.count() > 0 {
-        Some(*self.frames.iter().next().unwrap())
+  /// Combine the frame numbers of both sets at the precision of the most
+  /// precise, `None` if a frame overflows when rescaled to it
+  fn combine(&self, other: &Frames, operation: Operation) -> Option<Frames> {
+    fn rescale(frames: &Frames, precision: u32) -> Option<Cow<'_, Frames>> {
+      if frames.precision == precision {
+        Some(Cow::Borrowed(frames))
       } else {
-        None
+        frames.rescale(precision).map(Cow::Owned)
       }
-    };
-    first
+    }
+    let precision = self.precision.max(other.precision);
+    let (this, other) = (rescale(self, precision)?, rescale(other, precision)?);
+    Some(Frames { runs: this.runs.combine(&other.runs, operation), precision })
   }
 
-  pub fn last(&self) -> Option<u32> {
-    let last = {
-      if self.count() > 0 {
-        Some(*self.frames.iter().last().unwrap())
-      } else {
-        None
+  /// Frames in either set, also `self | other`
+  ///
+  /// # Panics
+  ///
+  /// If the sets differ in precision and a frame overflows at the finer one,
+  /// eg. `2000000000` with `1.5`. See `checked_union`.
+  pub fn union(&self, other: &Frames) -> Frames {
+    self.checked_union(other).expect("frame overflows when rescaled")
+  }
+
+  /// Frames in both sets, also `self & other`
+  ///
+  /// # Panics
+  ///
+  /// If a frame overflows at the finer precision, see `checked_intersection`.
+  pub fn intersection(&self, other: &Frames) -> Frames {
+    self.checked_intersection(other).expect("frame overflows when rescaled")
+  }
+
+  /// Frames in `self` but not in `other`, also `self - other`
+  ///
+  /// # Panics
+  ///
+  /// If a frame overflows at the finer precision, see `checked_difference`.
+  pub fn difference(&self, other: &Frames) -> Frames {
+    self.checked_difference(other).expect("frame overflows when rescaled")
+  }
+
+  /// Frames in exactly one of the sets, also `self ^ other`
+  ///
+  /// # Panics
+  ///
+  /// If a frame overflows at the finer precision, see `checked_symmetric_difference`.
+  pub fn symmetric_difference(&self, other: &Frames) -> Frames {
+    self.checked_symmetric_difference(other).expect("frame overflows when rescaled")
+  }
+
+  /// Frames in either set, `None` if a frame overflows at the finer precision
+  pub fn checked_union(&self, other: &Frames) -> Option<Frames> {
+    self.combine(other, Operation::Union)
+  }
+
+  /// Frames in both sets, `None` if a frame overflows at the finer precision
+  pub fn checked_intersection(&self, other: &Frames) -> Option<Frames> {
+    self.combine(other, Operation::Intersection)
+  }
+
+  /// Frames in `self` but not in `other`, `None` if a frame overflows at the finer precision
+  pub fn checked_difference(&self, other: &Frames) -> Option<Frames> {
+    self.combine(other, Operation::Difference)
+  }
+
+  /// Frames in exactly one of the sets, `None` if a frame overflows at the finer precision
+  pub fn checked_symmetric_difference(&self, other: &Frames) -> Option<Frames> {
+    self.combine(other, Operation::SymmetricDifference)
+  }
+
+  /// Step between every frame, `None` unless they all share one.
+  ///
+  /// Like `first`, `last`, `count` and `body`, this reads what the runs
+  /// already hold rather than walking the frames.
+  pub fn skip(&self) -> Option<u32> {
+    match self.runs.runs() {
+      [run] => Some(run.step),
+      _ => None,
+    }
+  }
+
+  pub fn first(&self) -> Option<Frame> {
+    self.runs.first()
+  }
+
+  pub fn last(&self) -> Option<Frame> {
+    self.runs.last()
+  }
+
+  pub fn body(&self) -> Body {
+    match self.runs.runs() {
+      [] | [_] => Body::Contiguous,
+      _ => Body::Indirect,
+    }
+  }
+
+  /// Decompose frames into the shortest list of ranges, eg. `1-499,501-1000`.
+  ///
+  /// A range is only worth writing when it covers three or more frames,
+  /// otherwise listing them is never longer. Within a run of constant step
+  /// the only useful places to end a range are its last or second to last
+  /// frame, so each frame has at most three candidates.
+  ///
+  /// Frames are visited a stored run at a time from the back. Once a range
+  /// is the cheapest start three frames before the end of a run, it stays
+  /// the cheapest for every earlier frame of the run, so only the tail of
+  /// each run is worked out frame by frame.
+  pub fn runs(&self) -> Vec<Range> {
+    let runs = self.runs.runs();
+    let precision = self.precision;
+    let width = |units: i64| match precision {
+      0 => digits(units),
+      _ => decimal(units, precision).len(),
+    };
+    let frame = |(run, offset): Position| {
+      let run = &runs[run];
+      (run.start as i64 + offset as i64 * run.step as i64) as Frame
+    };
+    let length = |run: usize| runs[run].count();
+    let next = |(run, offset): Position| match offset + 1 < length(run) {
+      true => (run, offset + 1),
+      false => (run + 1, 0),
+    };
+
+    // Last frame sharing the step from `position` to the frame after it
+    let extent = |(run, offset): Position| -> Position {
+      if offset + 1 < length(run) {
+        return (run, length(run) - 1);
+      }
+      match runs.get(run + 1) {
+        Some(after) if length(run + 1) > 1 && after.step == after.start.abs_diff(runs[run].stop) =>
+          (run + 1, length(run + 1) - 1),
+        Some(_) => (run + 1, 0),
+        None => (run, offset),
+      }
+    };
+
+    // Cheapest encoding of frames from each position onwards, and where its first chunk
+    // ends: worked out for the tail of each run, and as a constant plus the width of the
+    // first frame before that
+    let mut tails: Vec<Tail> = vec![Tail::default(); runs.len()];
+    let cost = |tails: &[Tail], (run, offset): Position| -> usize {
+      match tails.get(run) {
+        None => 0,
+        Some(tail) if offset >= tail.split => tail.chunks[(offset - tail.split) as usize].0,
+        Some(tail) => width(frame((run, offset)).into()) + tail.constant,
       }
     };
-    last
+    for run in (0..runs.len()).rev() {
+      let mut chunks: Vec<(usize, Position)> = Vec::new();
+      let mut offset = length(run);
+      while offset > 0 {
+        offset -= 1;
+        let index = (run, offset);
+
+        // Frames after `index` in this run are worked out but not yet in `tails`
+        let cost = |position: Position| match position.0 == run {
+          true => chunks[(length(run) - 1 - position.1) as usize].0,
+          false => cost(&tails, position),
+        };
+        let mut best = (width(frame(index).into()) + 1 + cost(next(index)), index);
+        let stops = match extent(index) {
+          last if last == index => vec![],
+          (after, 0) => vec![(after, 0)],
+          (after, offset) => vec![(after, offset), (after, offset - 1)],
+        };
+        for stop in stops {
+          let covered = match stop.0 == run {
+            true => stop.1 - offset,
+            false => stop.1 + 1,
+          };
+          if covered < 2 {
+            continue;
+          }
+          let step = frame(next(index)).abs_diff(frame(index));
+          let suffix = if step != 10u32.pow(precision) { 1 + width(step.into()) } else { 0 };
+          let candidate = width(frame(index).into()) + 1 + width(frame(stop).into()) + suffix + 1 + cost(next(stop));
+          if candidate < best.0 {
+            best = (candidate, stop);
+          }
+        }
+        chunks.push(best);
+        if offset + 3 < length(run) && best.1 != index {
+          tails[run].constant = best.0 - width(frame(index).into());
+          break;
+        }
+      }
+      chunks.reverse();
+      tails[run].split = offset;
+      tails[run].chunks = chunks;
+    }
+
+    let mut ranges = Vec::new();
+    let mut index = (0, 0);
+    while index.0 < runs.len() {
+      let tail = &tails[index.0];
+      let stop = match index.1 >= tail.split {
+        true => tail.chunks[(index.1 - tail.split) as usize].1,
+        false => tail.chunks[0].1,
+      };
+      let step = if stop != index { frame(next(index)).abs_diff(frame(index)) } else { 1 };
+      ranges.push(Range::new(frame(index), frame(stop), step, Stride::Fill).with_precision(precision));
+      index = next(stop);
+    }
+    ranges
   }
 
-  pub fn body(&self) -> Body {
-    if self.count() == 0 {
-      Body::Contiguous
-    } else {
-      match self.skip() {
-        Some(_) => Body::Contiguous,
-        _ => Body::Indirect,
+  /// Frames as the runs of constant step they are stored as, eg.
+  /// `1001-1002,1004-1005` for `1001,1002,1004,1005`. Each run takes the
+  /// frame after its first to set its step and keeps every following frame
+  /// on it, so the runs are the same for the same frames. A lone frame has
+  /// a step of one unit.
+  pub fn canonical_runs(&self) -> Vec<Range> {
+    self.runs.runs().iter()
+      .map(|run| Range::new(run.start, run.stop, run.step, Stride::Fill).with_precision(self.precision))
+      .collect()
+  }
+
+  /// Holes between the first and last frame, when a frame is expected
+  /// every `step` units from the first, eg. `4-6,9` for `1-3,7-8,10`.
+  /// A `step` of `0` expects one frame per whole frame. Frames off that
+  /// step neither fill nor open a hole.
+  pub fn gaps(&self, step: u32) -> Vec<Range> {
+    let step = self.expected(step);
+    let mut gaps: Vec<Range> = Vec::new();
+    let mut extend = |start: Frame, stop: Frame| match gaps.last_mut() {
+      Some(gap) if gap.stop().abs_diff(start) == step => *gap = Range::new(gap.start(), stop, step, Stride::Fill)
+        .with_precision(self.precision),
+      _ => gaps.push(Range::new(start, stop, step, Stride::Fill).with_precision(self.precision)),
+    };
+
+    // Holes on the step come whole, holes further apart are each a gap of their own
+    for run in self.runs.holes(step).runs() {
+      match run.start == run.stop || run.step == step {
+        true => extend(run.start, run.stop),
+        false => Run::frames(run).for_each(|frame| extend(frame, frame)),
       }
     }
+    gaps
+  }
+
+  /// Frames expected every `step` units that are absent, see `gaps`
+  pub fn missing(&self, step: u32) -> Frames {
+    Frames { runs: self.runs.holes(self.expected(step)), precision: self.precision }
+  }
+
+  /// Smallest distance between two frames in units, the step a sequence was
+  /// most likely rendered on. `None` with fewer than two frames.
+  pub fn min_step(&self) -> Option<u32> {
+    self.runs.min_step()
+  }
+
+  /// Step in units a frame is expected every, one whole frame for `0`
+  fn expected(&self, step: u32) -> u32 {
+    if step == 0 { 10u32.pow(self.precision) } else { step }
   }
 
-  pub fn frames(&self) -> &FrameNumbers {
-    &self.frames
+  /// Every frame in ascending order, in units
+  pub fn frames(&self) -> FrameIter<'_> {
+    self.runs.iter()
   }
 
+  /// Number of frames, saturating where a `usize` cannot hold them all
   pub fn count(&self) -> usize {
-    self.frames.len()
+    usize::try_from(self.runs.count()).unwrap_or(usize::MAX)
   }
+
+  /// Whether `frame`, in units, is in the set
+  pub fn contains(&self, frame: Frame) -> bool {
+    self.runs.contains(frame)
+  }
+
+  /// Add a frame in units, `false` if it was already there
+  pub fn insert(&mut self, frame: Frame) -> bool {
+    if self.contains(frame) {
+      return false;
+    }
+    self.runs = self.runs.combine(&Runs::from_frames(&[frame]), Operation::Union);
+    true
+  }
+
+  /// Take out a frame in units, `false` if it was not there
+  pub fn remove(&mut self, frame: Frame) -> bool {
+    if !self.contains(frame) {
+      return false;
+    }
+    self.runs = self.runs.combine(&Runs::from_frames(&[frame]), Operation::Difference);
+    true
+  }
+
+  /// Frames moved by `offset` units, eg. `1001-1100` for `1-100` and `1000`.
+  /// `None` if a frame would overflow.
+  pub fn offset(&self, offset: Frame) -> Option<Frames> {
+    let mut runs = Runs::default();
+    for run in self.runs.runs() {
+      runs.push(Run { start: run.start.checked_add(offset)?, stop: run.stop.checked_add(offset)?, step: run.step });
+    }
+    Some(Frames { runs, precision: self.precision })
+  }
+
+  /// Frames stretched by `factor` away from `origin`, rounded to the nearest
+  /// unit, eg. `1001-1199x2` for `1001-1100`, `2` and `1001`. Frames landing
+  /// on the same unit merge. `None` if the factor is not finite or a frame
+  /// would overflow.
+  pub fn retime(&self, factor: f64, origin: Frame) -> Option<Frames> {
+    if !factor.is_finite() {
+      return None;
+    }
+    let retime = |frame: Frame| {
+      let units = (origin as f64 + (frame as f64 - origin as f64) * factor).round();
+      if units < Frame::MIN as f64 || units > Frame::MAX as f64 {
+        None
+      } else {
+        Some(units as Frame)
+      }
+    };
+    let frames = self.frames().map(retime).collect::<Option<Vec<Frame>>>()?;
+    Some(Frames::with_precision(&frames, self.precision))
+  }
+
+  /// Frames from `first` to `last` units inclusive, empty if `first` is after `last`
+  pub fn clamp(&self, first: Frame, last: Frame) -> Frames {
+    let mut runs = Runs::default();
+    if let Some(range) = Run::new(first as i64, last as i64, 1) {
+      runs = self.runs.combine(&Runs::from_runs(&[range]), Operation::Intersection);
+    }
+    Frames { runs, precision: self.precision }
+  }
+
+  /// Frames, in units, for which `predicate` is true
+  pub fn filter<P: FnMut(Frame) -> bool>(&self, mut predicate: P) -> Frames {
+    let mut runs = Runs::default();
+    for frame in self.frames().filter(|&frame| predicate(frame)) {
+      runs.push(Run::single(frame));
+    }
+    Frames { runs, precision: self.precision }
+  }
+}
+
+impl Extend<Frame> for Frames {
+
+  /// Add frames in units
+  fn extend<I: IntoIterator<Item = Frame>>(&mut self, frames: I) {
+    let frames: Vec<Frame> = frames.into_iter().collect();
+    self.runs = self.runs.combine(&Runs::from_frames(&frames), Operation::Union);
+  }
+}
+
+/// A frame, as the index of its run and its offset within it
+type Position = (usize, u64);
+
+/// How to write the frames of a run from each position onwards, see `Frames::runs`
+#[derive(Debug, Clone, Default)]
+struct Tail {
+
+  /// First offset worked out frame by frame
+  split: u64,
+
+  /// Cost and last frame of the first chunk, from `split` to the end of the run
+  chunks: Vec<(usize, Position)>,
+
+  /// Cost of the frames before `split`, less the width of the first
+  constant: usize,
+}
+
+/// Number of characters needed to write a number, including its sign
+fn digits(value: i64) -> usize {
+  let sign = if value < 0 { 1 } else { 0 };
+  sign + value.unsigned_abs().checked_ilog10().map_or(1, |log| log as usize + 1)
 }
 
 #[cfg(test)]
@@ -125,7 +662,8 @@ mod tests {
 
   #[test]
   fn test_new() {
-    assert_eq!(Frames::new(&[0, 1, 2]).frames.len(), 3);
+    assert_eq!(Frames::new(&[0, 1, 2]).count(), 3);
+    assert_eq!(Frames::new(&[2, 0, 1, 2]).frames().collect::<Vec<_>>(), vec![0, 1, 2]);
   }
 
   #[test]
@@ -172,8 +710,425 @@ mod tests {
     assert_eq!(Frames::new(&[0]).token(), r#"0"#);
     assert_eq!(Frames::new(&[0,1]).token(), r#"0-1"#);
     assert_eq!(Frames::new(&[2,4]).count(), 2);
-    assert_eq!(Frames::new(&[2,4]).token(), r#"2-4"#);
+    assert_eq!(Frames::new(&[2,4]).token(), r#"2-4x2"#);
+    assert_eq!(Frames::new(&[10,20,30]).token(), r#"10-30x10"#);
+    assert_eq!(Frames::new(&[1,4]).token(), r#"1-4x3"#);
     assert_eq!(Frames::new(&[0,1,3]).token(), r#"0,1,3"#);
     assert_eq!(Frames::new(&[0,1,4,8,13]).token(), r#"0,1,4,8,13"#);
   }
+
+  #[test]
+  fn test_token_runs() {
+    let frames = |s: &str| s.parse::<Frames>().unwrap();
+    assert_eq!(frames("1-499,501-1000").token(), r#"1-499,501-1000"#);
+    assert_eq!(frames("1-100,102-200,210-300x10").token(), r#"1-100,102-200,210-300x10"#);
+    assert_eq!(frames("1,5-8").token(), r#"1,5-8"#);
+    assert_eq!(frames("1-10,12,14").token(), r#"1-10,12,14"#);
+    assert_eq!(frames("1-10,12,14,16").token(), r#"1-9,10-16x2"#);
+    assert_eq!(frames("1-3,7,20-40x5").token(), r#"1-3,7,20-40x5"#);
+    assert_eq!(frames("1-10y3").token(), r#"2,3,5,6,8,9"#);
+    assert_eq!(frames("1-11y4").token(), r#"2-4,6-8,10,11"#);
+    assert_eq!(frames("1-2y0.5").count(), 8);
+  }
+
+  #[test]
+  fn test_canonical_runs() {
+    let runs = |s: &str| -> Vec<String> {
+      s.parse::<Frames>().unwrap().canonical_runs().iter().map(|r| r.to_string()).collect()
+    };
+    assert!(runs("").is_empty());
+    assert_eq!(runs("7"), ["7"]);
+    assert_eq!(runs("1001,1002,1004,1005"), ["1001-1002", "1004-1005"]);
+    assert_eq!(runs("1-10,12,14,16"), ["1-10", "12-16x2"]);
+    assert_eq!(runs("1-3,7,20-40x5"), ["1-3", "7-20x13", "25-40x5"]);
+    assert_eq!(runs("1-2x0.5"), ["1-2x0.5"]);
+  }
+
+  #[test]
+  fn test_token_round_trip() {
+    let patterns = [
+      "",
+      "7",
+      "0-1",
+      "2-4x2",
+      "10-30x10",
+      "1-10x3",
+      "0,1,3",
+      "0,1,4,8,13",
+      "1-499,501-1000",
+      "1-100,102-200,210-300x10",
+      "1-10,12,14",
+      "1-10y3",
+      "5,10-1000x7,1001,1003-1010",
+    ];
+    for pattern in patterns.iter() {
+      let frames = pattern.parse::<Frames>().unwrap();
+      assert_eq!(frames.token().parse::<Frames>().unwrap(), frames);
+    }
+  }
+
+  #[test]
+  fn test_negative() {
+    let frames = "-10--1".parse::<Frames>().unwrap();
+    assert_eq!(frames.count(), 10);
+    assert_eq!(frames.first(), Some(-10));
+    assert_eq!(frames.last(), Some(-1));
+    assert_eq!(frames.skip(), Some(1));
+    assert_eq!(frames.token(), r#"-10--1"#);
+    assert_eq!(Frames::new(&[-10, 0, 10]).token(), r#"-10-10x10"#);
+    assert_eq!(Frames::new(&[-3, -2, -1, 0, 1, 5]).token(), r#"-3-1,5"#);
+    assert_eq!(Frames::new(&[Frame::MIN, Frame::MAX]).skip(), Some(u32::MAX));
+    let frames = "-5--1,1-3,10-20x5".parse::<Frames>().unwrap();
+    assert_eq!(frames.token().parse::<Frames>().unwrap(), frames);
+  }
+
+  #[test]
+  fn test_subframes() {
+    let frames = "1001-1002x0.25".parse::<Frames>().unwrap();
+    assert_eq!(frames.precision(), 2);
+    assert_eq!(frames.count(), 5);
+    assert_eq!(frames.first(), Some(100100));
+    assert_eq!(frames.skip(), Some(25));
+    assert_eq!(frames.token(), r#"1001-1002x0.25"#);
+    assert_eq!(frames.token().parse::<Frames>().unwrap(), frames);
+
+    let frames = "1,2.5,4-5".parse::<Frames>().unwrap();
+    assert_eq!(frames.frames().collect::<Vec<_>>(), vec![10, 25, 40, 50]);
+    assert_eq!(frames.token(), r#"1,2.5,4,5"#);
+    assert_eq!(frames.token().parse::<Frames>().unwrap(), frames);
+
+    let frames = Frames::with_precision(&[10, 15, 20, 30, 40, 50], 1);
+    assert_eq!(frames.token(), r#"1,1.5,2-5"#);
+    assert_eq!(frames.token().parse::<Frames>().unwrap(), frames);
+    assert_eq!(Frames::with_precision(&[-5], 1).token(), r#"-0.5"#);
+    assert_eq!("1.5".parse::<Frames>().unwrap(), Frames::with_precision(&[15], 1));
+    assert_eq!("1.5".parse::<Frames>().unwrap(), "1.50".parse::<Frames>().unwrap());
+    assert_eq!("1.5".parse::<Frames>().unwrap().precision(), 1);
+    assert_eq!("1".parse::<Frames>().unwrap(), "1.0".parse::<Frames>().unwrap());
+    assert_ne!("1".parse::<Frames>().unwrap(), "1.1".parse::<Frames>().unwrap());
+    assert_eq!(Frames::with_precision(&[100125, 100150], 3).token(), r#"100.125-100.15x0.025"#);
+    assert_eq!("1,3.000000001".parse::<Frames>().err(), Some(FramesError::Overflow { position: 2 }));
+    assert_eq!("2147483647,0.1".parse::<Frames>().err(), Some(FramesError::Overflow { position: 0 }));
+  }
+
+  #[test]
+  fn test_gaps() {
+    let gaps = |pattern: &str, step| -> Vec<String> {
+      let frames: Frames = pattern.parse().unwrap();
+      frames.gaps(step).iter().map(Range::to_string).collect()
+    };
+    assert_eq!(gaps("1-3,7-8,10", 1), vec!["4-6", "9"]);
+    assert_eq!(gaps("1-3,7-8,10", 0), vec!["4-6", "9"]);
+    assert_eq!(gaps("1,3,7,9,13", 2), vec!["5", "11"]);
+    assert_eq!(gaps("1-21x10,41", 10), vec!["31"]);
+    assert_eq!(gaps("1,2,3,5,9", 2), vec!["7"]);
+    assert_eq!(gaps("-5--3,1", 1), vec!["-2-0"]);
+    assert_eq!(gaps("1.5,2,3.5", 0), vec!["2.5"]);
+    assert_eq!(gaps("1,1.5,3", 5), vec!["2-2.5x0.5"]);
+    assert_eq!(gaps("1-9x2", 1), vec!["2", "4", "6", "8"]);
+    assert_eq!(gaps("1,5-9x2,10", 1), vec!["2-4", "6", "8"]);
+    assert_eq!(gaps("1-10000000x3", 3), Vec::<String>::new());
+    assert_eq!(gaps("1-5000000,9000001-9500000", 1), vec!["5000001-9000000"]);
+    assert!(gaps("1-100", 1).is_empty());
+    assert!(Frames::new(&[]).gaps(1).is_empty());
+  }
+
+  #[test]
+  fn test_missing() {
+    let frames: Frames = "1-3,7-8,10".parse().unwrap();
+    assert_eq!(frames.missing(1), Frames::new(&[4, 5, 6, 9]));
+    assert_eq!(frames.missing(1).token(), "4-6,9");
+    let frames: Frames = "1001-1100x2".parse().unwrap();
+    assert_eq!(frames.missing(1).count(), 49);
+    assert_eq!(frames.missing(2).count(), 0);
+    assert_eq!(frames.min_step(), Some(2));
+    let frames: Frames = "1-3000000x2".parse().unwrap();
+    assert_eq!(frames.missing(frames.min_step().unwrap() / 2).token(), "2-2999998x2");
+  }
+
+  #[test]
+  fn test_set_operations() {
+    let a: Frames = "1-10".parse().unwrap();
+    let b: Frames = "5-15".parse().unwrap();
+    assert_eq!(a.union(&b).token(), "1-15");
+    assert_eq!(a.intersection(&b).token(), "5-10");
+    assert_eq!(a.difference(&b).token(), "1-4");
+    assert_eq!(a.symmetric_difference(&b).token(), "1-4,11-15");
+    assert_eq!(&a | &b, a.union(&b));
+    assert_eq!(&a & &b, a.intersection(&b));
+    assert_eq!(&a - &b, a.difference(&b));
+    assert_eq!(&a ^ &b, a.symmetric_difference(&b));
+    assert_eq!(a.clone() - a.clone(), Frames::new(&[]));
+    let requested: Frames = "1001-1100".parse().unwrap();
+    let delivered: Frames = "1001-1049,1051-1100x2".parse().unwrap();
+    assert_eq!((requested - delivered).token(), "1050-1100x2");
+  }
+
+  #[test]
+  fn test_set_operations_precision() {
+    let frames: Frames = "1-3".parse().unwrap();
+    let subframes: Frames = "2-3x0.5".parse().unwrap();
+    let union = &frames | &subframes;
+    assert_eq!(union.precision(), 1);
+    assert_eq!(union.token(), "1,2,2.5,3");
+    assert_eq!((&frames & &subframes).token(), "2-3");
+    assert_eq!((&subframes - &frames).token(), "2.5");
+    assert_eq!(frames.rescale(2), Some(Frames::with_precision(&[100, 200, 300], 2)));
+    assert_eq!(subframes.rescale(0), None);
+    assert_eq!(Frames::new(&[Frame::MAX]).rescale(1), None);
+  }
+
+  #[test]
+  fn test_set_operations_overflow() {
+    let far = Frames::new(&[2_000_000_000]);
+    let subframe: Frames = "1.5".parse().unwrap();
+    assert_eq!(far.checked_union(&subframe), None);
+    assert_eq!(far.checked_intersection(&subframe), None);
+    assert_eq!(subframe.checked_difference(&far), None);
+    assert_eq!(far.checked_symmetric_difference(&subframe), None);
+    assert_eq!(far.checked_union(&Frames::new(&[1])).map(|frames| frames.token()), Some("1-2000000000x1999999999".to_owned()));
+  }
+
+  #[test]
+  #[should_panic(expected = "frame overflows when rescaled")]
+  fn test_set_operations_overflow_panics() {
+    let _ = &Frames::new(&[2_000_000_000]) | &"1.5".parse().unwrap();
+  }
+
+  #[test]
+  fn test_insert_remove() {
+    let mut frames: Frames = "1-10".parse().unwrap();
+    assert!(frames.insert(12));
+    assert!(!frames.insert(12));
+    assert!(frames.remove(5));
+    assert!(!frames.remove(5));
+    assert!(frames.contains(4) && !frames.contains(5));
+    assert_eq!((frames.count(), frames.body(), frames.skip()), (10, Body::Indirect, None));
+    frames.extend(vec![14, 16]);
+    assert_eq!(frames.token(), "1-4,6-9,10-16x2");
+    assert_eq!((frames.first(), frames.last(), frames.count()), (Some(1), Some(16), 12));
+    frames.extend(vec![5, 11, 13, 15]);
+    assert_eq!((frames.count(), frames.body(), frames.skip()), (16, Body::Contiguous, Some(1)));
+    let mut subframes = Frames::with_precision(&[10], 1);
+    subframes.insert(15);
+    assert_eq!(subframes.token(), "1-1.5x0.5");
+  }
+
+  #[test]
+  fn test_offset() {
+    let frames: Frames = "1-100".parse().unwrap();
+    assert_eq!(frames.offset(1000).unwrap().token(), "1001-1100");
+    assert_eq!(frames.offset(-11).unwrap().token(), "-10-89");
+    assert_eq!(frames.offset(Frame::MAX), None);
+  }
+
+  #[test]
+  fn test_retime() {
+    let frames: Frames = "1001-1100".parse().unwrap();
+    assert_eq!(frames.retime(2.0, 1001).unwrap().token(), "1001-1199x2");
+    assert_eq!(frames.retime(0.5, 1001).unwrap().token(), "1001-1051");
+    assert_eq!(frames.retime(1.0, 0).unwrap(), frames);
+    assert_eq!(frames.retime(f64::NAN, 0), None);
+    assert_eq!(frames.retime(1e10, 0), None);
+    let frames: Frames = "1-3x0.5".parse().unwrap();
+    assert_eq!(frames.retime(2.0, 10).unwrap().token(), "1-5");
+  }
+
+  #[test]
+  fn test_clamp_filter() {
+    let frames: Frames = "1-100".parse().unwrap();
+    assert_eq!(frames.clamp(-5, 10).token(), "1-10");
+    assert_eq!(frames.clamp(50, 40).count(), 0);
+    assert_eq!(frames.filter(|frame| frame % 10 == 0).token(), "10-100x10");
+  }
+
+  /// Shortest ranges worked out frame by frame, to check `Frames::runs` against
+  fn runs_by_frame(frames: &Frames) -> Vec<Range> {
+    let precision = frames.precision;
+    let frames: Vec<Frame> = frames.frames().collect();
+    let count = frames.len();
+    let width = |units: i64| match precision {
+      0 => digits(units),
+      _ => decimal(units, precision).len(),
+    };
+
+    // Index of the last frame sharing the step from `index` to `index + 1`
+    let mut extent: Vec<usize> = (0..count).collect();
+    if count > 1 {
+      extent[count - 2] = count - 1;
+    }
+    for index in (0..count.saturating_sub(2)).rev() {
+      let step = frames[index + 1].abs_diff(frames[index]);
+      extent[index] = if frames[index + 2].abs_diff(frames[index + 1]) == step {
+        extent[index + 1]
+      } else {
+        index + 1
+      };
+    }
+
+    // Cheapest encoding of frames from `index` onwards, and where its first chunk ends
+    let mut cost = vec![0; count + 1];
+    let mut end = vec![0; count];
+    for index in (0..count).rev() {
+      cost[index] = width(frames[index].into()) + 1 + cost[index + 1];
+      end[index] = index;
+      let last = extent[index];
+      for stop in [last, last.saturating_sub(1)] {
+        if stop < index + 2 {
+          continue;
+        }
+        let step = frames[index + 1].abs_diff(frames[index]);
+        let suffix = if step != 10u32.pow(precision) { 1 + width(step.into()) } else { 0 };
+        let candidate = width(frames[index].into()) + 1 + width(frames[stop].into()) + suffix + 1 + cost[stop + 1];
+        if candidate < cost[index] {
+          cost[index] = candidate;
+          end[index] = stop;
+        }
+      }
+    }
+
+    let mut runs = Vec::new();
+    let mut index = 0;
+    while index < count {
+      let stop = end[index];
+      let step = if stop > index { frames[index + 1].abs_diff(frames[index]) } else { 1 };
+      runs.push(Range::new(frames[index], frames[stop], step, Stride::Fill).with_precision(precision));
+      index = stop + 1;
+    }
+    runs
+  }
+
+  #[test]
+  fn test_runs_by_frame() {
+    let mut state: u64 = 0x9e37_79b9_7f4a_7c15;
+    let mut next = move |bound: u64| {
+      state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+      (state >> 33) % bound
+    };
+    for _ in 0..500 {
+      let mut units = Vec::new();
+      let mut frame = next(2000) as Frame - 1000;
+      for _ in 0..next(10) {
+        let step = [1, 1, 2, 3, 10, 25, 997][next(7) as usize];
+        for _ in 0..next(40) {
+          units.push(frame);
+          frame += step;
+        }
+        frame += next(30) as Frame;
+      }
+      let frames = Frames::with_precision(&units, next(3) as u32);
+      assert_eq!(frames.runs(), runs_by_frame(&frames), "{:?}", units);
+    }
+  }
+
+  #[test]
+  fn test_large() {
+    let parse = |pattern| Frames::parse_with_budget(pattern, u64::MAX).unwrap();
+    let frames = parse("1-100000000");
+    assert_eq!(frames.runs.runs().len(), 1);
+    assert_eq!(frames.count(), 100_000_000);
+    assert!(frames.contains(99_999_999));
+    let odd = parse("1-100000000x2");
+    assert_eq!((&frames - &odd).token(), "2-100000000x2");
+    assert_eq!(&frames & &odd, odd);
+    let mut frames = frames;
+    frames.remove(5000);
+    assert_eq!(frames.token(), "1-4999,5001-100000000");
+  }
+
+  #[test]
+  fn test_parse_unsorted() {
+    let units: Vec<Frame> = (0..40_000).map(|index: Frame| index.wrapping_mul(7919) % 100_003).collect();
+    let pattern: Vec<String> = units.iter().map(Frame::to_string).collect();
+    let frames: Frames = pattern.join(",").parse().unwrap();
+    assert_eq!(frames, Frames::new(&units));
+    assert_eq!(frames.count(), 40_000);
+    assert_eq!("5-9,1-3,2-6x2,20".parse::<Frames>().unwrap().token(), "1-9,20");
+  }
+
+  #[test]
+  fn test_budget() {
+    assert_eq!("0-2000000000".parse::<Frames>(),
+               Err(FramesError::TooManyFrames { position: 0, budget: Frames::DEFAULT_BUDGET }));
+    assert_eq!(Frames::parse_with_budget("1-10,20-29", 20).map(|frames| frames.count()), Ok(20));
+    assert_eq!(Frames::parse_with_budget("1-10,20-30", 20),
+               Err(FramesError::TooManyFrames { position: 5, budget: 20 }));
+    assert_eq!(Frames::parse_with_budget("1-10, 1-2000000000y2", 20),
+               Err(FramesError::TooManyFrames { position: 6, budget: 20 }));
+    assert_eq!("0-2000000000y1".parse::<Frames>(), Ok(Frames::new(&[])));
+    assert_eq!(Frames::parse_with_budget("0-2000000000y2", u64::MAX).map(|frames| frames.token()),
+               Ok("1-1999999999x2".to_owned()));
+    assert_eq!("0-2000000000y1,0-2000000000y1,5".parse::<Frames>().map(|frames| frames.count()), Ok(1));
+    assert_eq!(Frames::parse_with_budget("1-10,1-10", 15),
+               Err(FramesError::TooManyFrames { position: 5, budget: 15 }));
+    let all = Frames::parse_with_budget("-2147483648-2147483647", u64::MAX).unwrap();
+    assert_eq!(all.count() as u64, 1 << 32);
+    assert_eq!(all.token(), "-2147483648-2147483647");
+    assert_eq!(Frames::new(&[Frame::MIN, Frame::MAX]).token(), "-2147483648-2147483647x4294967295");
+  }
+
+  #[test]
+  fn test_missing_large() {
+    let frames = Frames::new(&[0, 2_000_000_000]);
+    assert_eq!(frames.missing(1).token(), "1-1999999999");
+    assert_eq!(frames.missing(1).count(), 1_999_999_999);
+  }
+
+  #[cfg(feature = "serde")]
+  #[test]
+  fn test_serde() {
+    let frames: Frames = "1-10,12,1.5".parse().unwrap();
+    assert_eq!(serde_json::to_string(&frames).unwrap(), r#""1,1.5,2-10,12""#);
+    assert_eq!(serde_json::from_str::<Frames>(r#""1,1.5,2-10,12""#).unwrap(), frames);
+    assert_eq!(serde_json::from_str::<Frames>("[3, 1, 2]").unwrap(), Frames::new(&[1, 2, 3]));
+    assert_eq!(serde_json::from_str::<Frames>(r#""""#).unwrap(), Frames::new(&[]));
+    assert_eq!(serde_json::to_string(&Frames::new(&[])).unwrap(), r#""""#);
+    let subframes = Frames::with_precision(&[100150, 100200], 2);
+    assert_eq!(serde_json::to_string(&subframes).unwrap(), r#""1001.50-1002x0.5""#);
+    assert_eq!(serde_json::from_str::<Frames>(r#""1001.50-1002x0.5""#).unwrap().precision(), 2);
+    let whole = Frames::with_precision(&[100, 200], 2);
+    assert_eq!(serde_json::to_string(&whole).unwrap(), r#""1.00-2""#);
+    assert_eq!(serde_json::from_str::<Frames>(r#""1.00-2""#).unwrap().precision(), 2);
+    let error = serde_json::from_str::<Frames>(r#""1-a""#).unwrap_err();
+    assert!(error.to_string().starts_with("invalid character 'a' at position 2"));
+    assert!(serde_json::from_str::<Frames>("[1.5]").is_err());
+    assert!(serde_json::from_str::<Frames>("{}").is_err());
+  }
+
+  #[test]
+  fn test_digits() {
+    assert_eq!(digits(0), 1);
+    assert_eq!(digits(9), 1);
+    assert_eq!(digits(10), 2);
+    assert_eq!(digits(-1), 2);
+    assert_eq!(digits(-10), 3);
+    assert_eq!(digits(Frame::MAX.into()), 10);
+    assert_eq!(digits(Frame::MIN.into()), 11);
+    assert_eq!(digits(u32::MAX.into()), 10);
+  }
+
+  #[test]
+  fn test_from_str() {
+    let frames = |s: &str| s.parse::<Frames>().unwrap().frames().collect::<Vec<_>>();
+    assert_eq!(frames("3"), vec![3]);
+    assert_eq!(frames("1-5"), vec![1, 2, 3, 4, 5]);
+    assert_eq!(frames("1-10x2"), vec![1, 3, 5, 7, 9]);
+    assert_eq!(frames("1-10y3"), vec![2, 3, 5, 6, 8, 9]);
+    assert_eq!(frames("1,3,5-9"), vec![1, 3, 5, 6, 7, 8, 9]);
+    assert_eq!(frames("5-7,1,6"), vec![1, 5, 6, 7]);
+    assert_eq!(frames("1, 3 ,5-6"), vec![1, 3, 5, 6]);
+    assert_eq!(Frames::try_from("1-3").unwrap().count(), 3);
+    assert_eq!(frames(""), Vec::<Frame>::new());
+    assert_eq!(frames(" "), Vec::<Frame>::new());
+  }
+
+  #[test]
+  fn test_from_str_error() {
+    assert_eq!(",".parse::<Frames>().err(), Some(FramesError::Empty { position: 0 }));
+    assert_eq!("1,,3".parse::<Frames>().err(), Some(FramesError::Empty { position: 2 }));
+    assert_eq!("1,3-1".parse::<Frames>().err(), Some(FramesError::ReversedRange { position: 4 }));
+    assert_eq!("1, 2-4q".parse::<Frames>().err().map(|e| e.position()), Some(6));
+    assert_eq!(Frames::try_from("1,a").err(),
+               Some(FramesError::InvalidCharacter { position: 2, character: 'a' }));
+  }
 }
\ No newline at end of file
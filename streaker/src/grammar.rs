@@ -0,0 +1,211 @@
+/// Where the frame number sits in a file name
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Rule {
+
+  /// Last group of digits before the extension, eg. `render_1001_beauty.exr`
+  LastDigits,
+
+  /// Digits directly before the extension, eg. `render_1001.exr` or `render1001.exr`
+  BeforeExtension,
+
+  /// Digits between a delimiter and the extension, eg. `render.1001.exr` for `.`
+  Delimiter(char),
+}
+
+/// How file names are split into prefix, frame number and suffix
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Grammar {
+  rule: Rule,
+  extension_parts: usize,
+  subframes: bool,
+}
+
+/// Pieces of a sequence file name, eg. `render` `.` `1001` `.exr`.
+/// Negative frame numbers keep their sign in `digits`, eg. `-0010`,
+/// and subframes their fraction, eg. `1001.25`.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct Parts<'a> {
+  pub(crate) prefix: &'a str,
+  pub(crate) separator: &'a str,
+  pub(crate) digits: &'a str,
+  pub(crate) suffix: &'a str,
+}
+
+impl Default for Grammar {
+
+  /// `name.frame.ext`, allowing extensions such as `tar.gz`
+  fn default() -> Self {
+    Grammar::new(Rule::Delimiter('.'))
+  }
+}
+
+impl Grammar {
+
+  /// Constructor, allowing extensions of up to two parts
+  pub fn new(rule: Rule) -> Self {
+    Grammar { rule, extension_parts: 2, subframes: false }
+  }
+
+  /// Most dot separated parts an extension may have, eg. `2` for `tar.gz`.
+  /// Parts that are frame numbers are never part of the extension.
+  pub fn extension_parts(mut self, parts: usize) -> Self {
+    self.extension_parts = parts;
+    self
+  }
+
+  /// Read a decimal part after the frame number as a subframe, eg.
+  /// `sim.1001.25.bgeo`. Off by default as it also reads names such as
+  /// `shot.010.1001.exr` as subframes.
+  pub fn subframes(mut self, subframes: bool) -> Self {
+    self.subframes = subframes;
+    self
+  }
+
+  pub fn rule(&self) -> Rule {
+    self.rule
+  }
+
+  /// Split a file name at its extension, eg. `plate.1001` and `.tar.gz`
+  fn split_extension<'a>(&self, name: &'a str) -> (&'a str, &'a str) {
+    let mut stem = name;
+    for _ in 0..self.extension_parts {
+      match stem.rfind('.') {
+        Some(dot) if dot > 0 => {
+          let part = &stem[dot + 1..];
+          let digits = part.strip_prefix('-').unwrap_or(part);
+          if digits.is_empty() || digits.bytes().all(|b| b.is_ascii_digit()) {
+            break;
+          }
+          stem = &stem[..dot];
+        }
+        _ => break,
+      }
+    }
+    (stem, &name[stem.len()..])
+  }
+
+  /// Split a file name into its parts, `None` if it has no frame number
+  pub(crate) fn split<'a>(&self, name: &'a str) -> Option<Parts<'a>> {
+    let (stem, _) = self.split_extension(name);
+    let bytes = stem.as_bytes();
+    let end = match self.rule {
+      Rule::LastDigits => bytes.iter().rposition(u8::is_ascii_digit)? + 1,
+      Rule::BeforeExtension | Rule::Delimiter(_) => stem.len(),
+    };
+    let mut start = bytes[..end]
+      .iter()
+      .rposition(|b| !b.is_ascii_digit())
+      .map_or(0, |index| index + 1);
+    if start == end {
+      return None;
+    }
+
+    // Digits after a dot are the fraction of a subframe, eg. `1001.25`
+    if self.subframes && start > 1 && bytes[start - 1] == b'.' && bytes[start - 2].is_ascii_digit() {
+      start = bytes[..start - 1]
+        .iter()
+        .rposition(|b| !b.is_ascii_digit())
+        .map_or(0, |index| index + 1);
+    }
+
+    // A dash is the sign of a negative frame unless it follows the name, eg. `render.-0010.exr`
+    if start > 0 && bytes[start - 1] == b'-' {
+      let sign = stem[..start - 1].chars().next_back().is_none_or(|c| !c.is_alphanumeric());
+      if sign {
+        start -= 1;
+      }
+    }
+
+    let before = stem[..start].chars().next_back();
+    let separator = match (self.rule, before) {
+      (Rule::Delimiter(delimiter), Some(c)) if c == delimiter => c.len_utf8(),
+      (Rule::Delimiter(_), _) => return None,
+      (_, Some(c)) if !c.is_alphanumeric() => c.len_utf8(),
+      _ => 0,
+    };
+    Some(Parts {
+      prefix: &stem[..start - separator],
+      separator: &stem[start - separator..start],
+      digits: &stem[start..end],
+      suffix: &name[end..],
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+
+  use super::*;
+
+  fn split(rule: Rule, name: &str) -> Option<(&str, &str, &str, &str)> {
+    Grammar::new(rule)
+      .split(name)
+      .map(|p| (p.prefix, p.separator, p.digits, p.suffix))
+  }
+
+  #[test]
+  fn test_split_extension() {
+    let grammar = Grammar::default();
+    assert_eq!(grammar.split_extension("render.1001.exr"), ("render.1001", ".exr"));
+    assert_eq!(grammar.split_extension("plate.1001.tar.gz"), ("plate.1001", ".tar.gz"));
+    assert_eq!(grammar.split_extension("render.1001"), ("render.1001", ""));
+    assert_eq!(grammar.split_extension(".hidden"), (".hidden", ""));
+    assert_eq!(grammar.split_extension("file.-0010.exr"), ("file.-0010", ".exr"));
+    assert_eq!(grammar.split_extension("a.b.c.d"), ("a.b", ".c.d"));
+    assert_eq!(grammar.clone().extension_parts(1).split_extension("a.b.c.d"), ("a.b.c", ".d"));
+    assert_eq!(grammar.extension_parts(0).split_extension("a.exr"), ("a.exr", ""));
+  }
+
+  #[test]
+  fn test_split_delimiter() {
+    let rule = Rule::Delimiter('.');
+    assert_eq!(split(rule, "render.1001.exr"), Some(("render", ".", "1001", ".exr")));
+    assert_eq!(split(rule, "shot_010-comp_v003.1001.exr"),
+               Some(("shot_010-comp_v003", ".", "1001", ".exr")));
+    assert_eq!(split(rule, "plate.1001.tar.gz"), Some(("plate", ".", "1001", ".tar.gz")));
+    assert_eq!(split(rule, "render.1001"), Some(("render", ".", "1001", "")));
+    assert_eq!(split(rule, "render_1001.exr"), None);
+    assert_eq!(split(rule, "render.exr"), None);
+    assert_eq!(split(rule, "render.10a1.exr"), None);
+    assert_eq!(split(rule, "file.-0010.exr"), Some(("file", ".", "-0010", ".exr")));
+    assert_eq!(split(rule, "file-0010.exr"), None);
+    assert_eq!(split(Rule::Delimiter('_'), "render_1001.exr"), Some(("render", "_", "1001", ".exr")));
+  }
+
+  #[test]
+  fn test_split_before_extension() {
+    let rule = Rule::BeforeExtension;
+    assert_eq!(split(rule, "render_1001.exr"), Some(("render", "_", "1001", ".exr")));
+    assert_eq!(split(rule, "render1001.exr"), Some(("render", "", "1001", ".exr")));
+    assert_eq!(split(rule, "render.1001.exr"), Some(("render", ".", "1001", ".exr")));
+    assert_eq!(split(rule, "1001.exr"), Some(("", "", "1001", ".exr")));
+    assert_eq!(split(rule, "render_1001_beauty.exr"), None);
+    assert_eq!(split(rule, "render-1001.exr"), Some(("render", "-", "1001", ".exr")));
+    assert_eq!(split(rule, "render_-010.exr"), Some(("render", "_", "-010", ".exr")));
+    assert_eq!(split(rule, "render--010.exr"), Some(("render", "-", "-010", ".exr")));
+    assert_eq!(split(rule, "-010.exr"), Some(("", "", "-010", ".exr")));
+  }
+
+  #[test]
+  fn test_split_subframes() {
+    let grammar = Grammar::default().subframes(true);
+    let split = |name| grammar.split(name).map(|p| (p.prefix, p.separator, p.digits, p.suffix));
+    assert_eq!(split("sim.1001.25.bgeo"), Some(("sim", ".", "1001.25", ".bgeo")));
+    assert_eq!(split("cache.0012.5.abc"), Some(("cache", ".", "0012.5", ".abc")));
+    assert_eq!(split("cache.-0012.5.abc"), Some(("cache", ".", "-0012.5", ".abc")));
+    assert_eq!(split("sim.1001.bgeo"), Some(("sim", ".", "1001", ".bgeo")));
+    assert_eq!(split("sim.1001.25"), Some(("sim", ".", "1001.25", "")));
+    assert_eq!(split("v1001.25.bgeo"), None);
+    assert_eq!(Grammar::default().split("sim.1001.25.bgeo").map(|p| p.digits), Some("25"));
+    let grammar = Grammar::new(Rule::BeforeExtension).subframes(true);
+    assert_eq!(grammar.split("sim_1001.5.bgeo").map(|p| (p.prefix, p.digits)), Some(("sim", "1001.5")));
+  }
+
+  #[test]
+  fn test_split_last_digits() {
+    let rule = Rule::LastDigits;
+    assert_eq!(split(rule, "render_1001_beauty.exr"), Some(("render", "_", "1001", "_beauty.exr")));
+    assert_eq!(split(rule, "v003_render.1001.mp4"), Some(("v003_render", ".", "1001", ".mp4")));
+    assert_eq!(split(rule, "render.exr"), None);
+  }
+}
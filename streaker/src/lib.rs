@@ -1,3 +1,13 @@
+//! Frame sequences, eg. `render.1001-1100#.exr`.
+//!
+//! ```
+//! use streaker::prelude::*;
+//!
+//! let frames: Frames = "1-100".parse().unwrap();
+//! let streak = Streak::new("render", ".", Padding::new(4), frames, ".exr");
+//! assert_eq!(streak.to_string(), "render.1-100#.exr");
+//! ```
+
 #[cfg(test)]
 mod tests {
     #[test]
@@ -8,5 +18,30 @@ mod tests {
 
 mod padding;
 mod frames;
+mod grammar;
+mod pattern;
+mod range;
+mod runs;
+mod scan;
 mod streak;
-mod traits;
\ No newline at end of file
+mod traits;
+mod walk;
+
+#[cfg(test)]
+mod fixture;
+
+pub use crate::frames::{Body, Frame, Frames};
+pub use crate::grammar::{Grammar, Rule};
+pub use crate::padding::{Decimals, Notation, Padding, PaddingError, Width};
+pub use crate::pattern::PatternError;
+pub use crate::range::{FramesError, Range, Stride};
+pub use crate::runs::FrameIter;
+pub use crate::scan::{scan, scan_with, Scan};
+pub use crate::streak::{Paths, Streak};
+pub use crate::traits::Token;
+pub use crate::walk::Walk;
+
+/// Everything needed to build and render sequences
+pub mod prelude {
+  pub use crate::{scan, Frames, Padding, Scan, Streak, Token, Walk};
+}
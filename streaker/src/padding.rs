@@ -1,44 +1,357 @@
 use std::convert::TryFrom;
+use std::fmt;
+use crate::frames::Frame;
+use crate::range::{decimal, fixed};
+use crate::traits::Token;
 
+/// Convention used to write padding in a file name
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "lowercase"))]
+pub enum Notation {
+
+  /// `@` per digit and `#` per four digits, eg. `@#` for five
+  Streaker,
+
+  /// `#` per digit, as used by Nuke, eg. `####` for four
+  Hash,
+
+  /// printf style, eg. `%04d`, or `%d` for unpadded
+  Printf,
+
+  /// Houdini frame variable, eg. `$F4`, or `$F` for unpadded
+  Houdini,
+
+  /// Maya frame token `<f>`, which does not record a width
+  Maya,
+
+  /// Mari and Maya UDIM tile token `<UDIM>`, always four digits
+  Udim,
+}
+
+/// What is known about a padding width
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Width {
+
+  /// Frames are zero padded to exactly this width, wider frames overflow it,
+  /// eg. `0999`, `1000` and `10000` for four
+  Exact,
+
+  /// Frames are at least this wide but none needed zero padding, so any
+  /// padding up to this width names the same files, eg. `1001` to `1100`
+  Minimum,
+}
+
+/// How many decimal places the fraction of a subframe is written with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "lowercase"))]
+pub enum Decimals {
+
+  /// Exactly the precision of the frames, eg. `1001.50` and `1002.00` for two places
+  #[default]
+  Fixed,
+
+  /// Without trailing zeros, as Houdini's `$FF` writes them, eg. `1001.5` and `1002`
+  Trimmed,
+}
+
+/// Failure to parse padding such as `@#`, `%04d` or `$F4`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PaddingError {
+
+  /// Padding is empty where its notation needs a token
+  Empty { position: usize },
+
+  /// Unexpected character, eg. the `x` of `%04x`
+  InvalidCharacter { position: usize, character: char },
+
+  /// Padding ends before its token does, eg. `%04`
+  Truncated { position: usize },
+
+  /// Padding is wider than `Padding::MAX_VALUE`
+  Overflow { position: usize },
+}
+
+impl PaddingError {
+
+  /// Byte offset of the failure within the parsed padding
+  pub fn position(&self) -> usize {
+    match *self {
+      PaddingError::Empty { position } => position,
+      PaddingError::InvalidCharacter { position, .. } => position,
+      PaddingError::Truncated { position } => position,
+      PaddingError::Overflow { position } => position,
+    }
+  }
+
+  /// Same failure, `offset` bytes further into a larger string
+  pub(crate) fn shift(self, offset: usize) -> Self {
+    match self {
+      PaddingError::Empty { position } =>
+        PaddingError::Empty { position: position + offset },
+      PaddingError::InvalidCharacter { position, character } =>
+        PaddingError::InvalidCharacter { position: position + offset, character },
+      PaddingError::Truncated { position } =>
+        PaddingError::Truncated { position: position + offset },
+      PaddingError::Overflow { position } =>
+        PaddingError::Overflow { position: position + offset },
+    }
+  }
+}
+
+impl fmt::Display for PaddingError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      PaddingError::Empty { position } =>
+        write!(f, "empty padding at position {}", position),
+      PaddingError::InvalidCharacter { position, character } =>
+        write!(f, "invalid padding character '{}' at position {}", character, position),
+      PaddingError::Truncated { position } =>
+        write!(f, "padding ends early at position {}", position),
+      PaddingError::Overflow { position } =>
+        write!(f, "padding too wide at position {}", position),
+    }
+  }
+}
+
+impl std::error::Error for PaddingError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize),
+           serde(into = "Fields", try_from = "Fields"))]
 pub struct Padding {
   value: u32,
+  notation: Notation,
+  width: Width,
+}
+
+/// Padding as serde writes it, eg. `{"width": 4, "notation": "printf"}`,
+/// with `"minimum": true` when the width is only a minimum
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename = "Padding")]
+struct Fields {
+  width: u32,
+  notation: Notation,
+  #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+  minimum: bool,
+}
+
+#[cfg(feature = "serde")]
+impl From<Padding> for Fields {
+  fn from(padding: Padding) -> Self {
+    Fields { width: padding.value, notation: padding.notation, minimum: padding.width == Width::Minimum }
+  }
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<Fields> for Padding {
+  type Error = PaddingError;
+
+  fn try_from(fields: Fields) -> Result<Self, Self::Error> {
+    if fields.width > Padding::MAX_VALUE {
+      return Err(PaddingError::Overflow { position: 0 });
+    }
+    let width = if fields.minimum { Width::Minimum } else { Width::Exact };
+    Ok(Padding { value: fields.width, notation: fields.notation, width })
+  }
 }
 
 impl TryFrom<&str> for Padding {
-  type Error = String;
+  type Error = PaddingError;
 
   fn try_from(pattern: &str) -> Result<Self, Self::Error> {
-    if pattern.chars().all(|c| r#"@#"#.contains(c)) {
-      let value = pattern.chars().into_iter()
-        .map(|chr| {
-          match chr {
+    Padding::parse(pattern, Notation::Streaker)
+  }
+}
+
+impl Padding {
+
+  /// Widest padding, well past the widest frame
+  pub const MAX_VALUE: u32 = 64;
+
+  /// Constructor
+  ///
+  /// # Panics
+  ///
+  /// If `value` is more than `Padding::MAX_VALUE`.
+  pub fn new(value: u32) -> Self {
+    assert!(value <= Padding::MAX_VALUE, "padding of {} is too wide", value);
+    Padding { value, notation: Notation::Streaker, width: Width::Exact }
+  }
+
+  /// Padding of frames at least `value` wide, where the actual padding may be narrower
+  ///
+  /// # Panics
+  ///
+  /// If `value` is more than `Padding::MAX_VALUE`.
+  pub fn minimum(value: u32) -> Self {
+    Padding { width: Width::Minimum, ..Padding::new(value) }
+  }
+
+  pub fn width(&self) -> Width {
+    self.width
+  }
+
+  /// Whether a narrower padding would name the same files
+  pub fn is_ambiguous(&self) -> bool {
+    self.width == Width::Minimum && self.value > 1
+  }
+
+  /// Same padding, written with `notation`
+  pub fn with_notation(mut self, notation: Notation) -> Self {
+    self.notation = notation;
+    self
+  }
+
+  /// Parse padding written with `notation`, eg. `%04d` for `Notation::Printf`
+  pub fn parse(pattern: &str, notation: Notation) -> Result<Self, PaddingError> {
+    let invalid = |position: usize| match pattern[position..].chars().next() {
+      Some(character) => PaddingError::InvalidCharacter { position, character },
+      None => PaddingError::Truncated { position },
+    };
+
+    // Bytes of a literal token `pattern` starts with
+    let literal = |token: &str| match pattern.bytes().zip(token.bytes()).position(|(a, b)| a != b) {
+      Some(position) => Err(invalid(position)),
+      None if pattern.len() < token.len() => Err(PaddingError::Truncated { position: pattern.len() }),
+      None => Ok(token.len()),
+    };
+
+    // Width written in decimal from `start` to `end`, zero when there are no digits
+    let width = |start: usize, end: usize| -> Result<u32, PaddingError> {
+      let digits = &pattern[start..end];
+      match digits.find(|c: char| !c.is_ascii_digit()) {
+        Some(index) => Err(invalid(start + index)),
+        None if digits.is_empty() => Ok(0),
+        None => digits.parse().ok()
+          .filter(|&width| width <= Padding::MAX_VALUE)
+          .ok_or(PaddingError::Overflow { position: start }),
+      }
+    };
+
+    // Padding that is nothing but a token, eg. `<f>`
+    let exact = |token: &str| match literal(token)? {
+      end if end < pattern.len() => Err(invalid(end)),
+      _ => Ok(()),
+    };
+
+    let symbols = |allowed: &str| match pattern.find(|c| !allowed.contains(c)) {
+      Some(position) => Err(invalid(position)),
+      None => Ok(()),
+    };
+
+    if pattern.is_empty() && !matches!(notation, Notation::Streaker | Notation::Hash) {
+      return Err(PaddingError::Empty { position: 0 });
+    }
+    let value = match notation {
+      Notation::Streaker => {
+        symbols("@#")?;
+        let mut value = 0;
+        for (position, chr) in pattern.char_indices() {
+          value += match chr {
             '#' => 4,
             '@' => 1,
             _ => 0,
+          };
+          if value > Padding::MAX_VALUE {
+            return Err(PaddingError::Overflow { position });
           }
-        }).sum();
-      Ok(Padding::new(value))
-    } else {
-      Err(format!("Invalid pattern: {}", pattern))
+        }
+        value
+      }
+      Notation::Hash => {
+        symbols("#")?;
+        if pattern.len() > Padding::MAX_VALUE as usize {
+          return Err(PaddingError::Overflow { position: Padding::MAX_VALUE as usize });
+        }
+        pattern.len() as u32
+      }
+      Notation::Printf => {
+        let start = literal("%")?;
+        let end = start + pattern[start..].find(|c: char| !c.is_ascii_digit()).unwrap_or(pattern.len() - start);
+        let value = match &pattern[start..end] {
+          "" => 0,
+          digits if digits.starts_with('0') => width(start + 1, end)?,
+          _ => return Err(invalid(start)),
+        };
+        match &pattern[end..] {
+          "d" => value,
+          rest if rest.starts_with('d') => return Err(invalid(end + 1)),
+          _ => return Err(invalid(end)),
+        }
+      }
+      Notation::Houdini => width(literal("$F")?, pattern.len())?,
+      Notation::Maya => {
+        exact("<f>")?;
+        0
+      }
+      Notation::Udim => {
+        exact("<UDIM>")?;
+        4
+      }
+    };
+    Ok(Padding { value, notation, width: Width::Exact })
+  }
+
+  /// Write this padding with `notation`. Maya and UDIM tokens do not
+  /// record a width, so they are the same whatever the padding.
+  pub fn render(&self, notation: Notation) -> String {
+    match notation {
+      Notation::Streaker => {
+        let fours = (self.value / 4) as usize;
+        let ones = ::std::cmp::max(if fours > 0 {0} else {1},(self.value % 4) as usize);
+        format!("{}{}",
+                "@".repeat(ones),
+                "#".repeat(fours),
+        )
+      }
+      Notation::Hash => "#".repeat(self.value.max(1) as usize),
+      Notation::Printf if self.value > 1 => format!("%0{}d", self.value),
+      Notation::Printf => "%d".to_owned(),
+      Notation::Houdini if self.value > 1 => format!("$F{}", self.value),
+      Notation::Houdini => "$F".to_owned(),
+      Notation::Maya => "<f>".to_owned(),
+      Notation::Udim => "<UDIM>".to_owned(),
     }
   }
-}
 
-impl Padding {
+  pub fn notation(&self) -> Notation {
+    self.notation
+  }
 
-  /// Constructor
-  pub fn new(value: u32) -> Self {
-    Padding { value }
+  /// Minimum number of characters a frame is written with. Like printf,
+  /// the sign of a negative frame counts towards it, eg. `-010` for four.
+  pub fn value(&self) -> u32 {
+    self.value
+  }
+
+  /// Write a frame zero padded to this width, eg. `0010` or `-010`
+  pub fn format(&self, frame: Frame) -> String {
+    format!("{:0width$}", frame, width = self.value as usize)
   }
 
-  /// Format token from padding value
-  pub fn token(&self) -> String {
-    let fours = (self.value / 4) as usize;
-    let ones = ::std::cmp::max(if fours > 0 {0} else {1},(self.value % 4) as usize);
-    format!("{}{}",
-            "@".repeat(ones),
-            "#".repeat(fours),
-    )
+  /// Write a subframe given in units of `10^-precision` frames, padding
+  /// only its integer part, eg. `0010.25` or `-010.5` for four
+  pub fn format_subframe(&self, units: Frame, precision: u32, decimals: Decimals) -> String {
+    let text = match decimals {
+      Decimals::Fixed => fixed(units.into(), precision),
+      Decimals::Trimmed => decimal(units.into(), precision),
+    };
+    let (sign, number) = match text.strip_prefix('-') {
+      Some(number) => ("-", number),
+      None => ("", text.as_str()),
+    };
+    let integer = number.find('.').unwrap_or(number.len());
+    let zeros = (self.value as usize).saturating_sub(sign.len() + integer);
+    format!("{}{}{}", sign, "0".repeat(zeros), number)
+  }
+}
+
+impl Token for Padding {
+
+  /// Format token from padding value, in its own notation
+  fn token(&self) -> String {
+    self.render(self.notation)
   }
 }
 
@@ -51,6 +364,19 @@ mod tests {
   fn test_new() {
     assert_eq!(Padding::new(0).value, 0);
     assert_eq!(Padding::new(1).value, 1);
+    assert_eq!(Padding::new(Padding::MAX_VALUE).value, Padding::MAX_VALUE);
+  }
+
+  #[test]
+  #[should_panic(expected = "too wide")]
+  fn test_new_too_wide() {
+    Padding::new(u32::MAX);
+  }
+
+  #[test]
+  #[should_panic(expected = "too wide")]
+  fn test_minimum_too_wide() {
+    Padding::minimum(Padding::MAX_VALUE + 1);
   }
 
   #[test]
@@ -76,6 +402,123 @@ mod tests {
     assert!(Padding::try_from("#d").is_err());
     assert!(Padding::try_from("@d").is_err());
     assert!(Padding::try_from("@#dasd_1").is_err());
+    assert_eq!(Padding::try_from("@#d"), Err(PaddingError::InvalidCharacter { position: 2, character: 'd' }));
+  }
+
+  #[test]
+  fn test_parse() {
+    let parse = |pattern, notation| Padding::parse(pattern, notation).map(|p| p.value());
+    assert_eq!(parse("@#", Notation::Streaker), Ok(5));
+    assert_eq!(parse("####", Notation::Hash), Ok(4));
+    assert_eq!(parse("#", Notation::Hash), Ok(1));
+    assert_eq!(parse("%04d", Notation::Printf), Ok(4));
+    assert_eq!(parse("%010d", Notation::Printf), Ok(10));
+    assert_eq!(parse("%d", Notation::Printf), Ok(0));
+    assert_eq!(parse("$F4", Notation::Houdini), Ok(4));
+    assert_eq!(parse("$F", Notation::Houdini), Ok(0));
+    assert_eq!(parse("<f>", Notation::Maya), Ok(0));
+    assert_eq!(parse("<UDIM>", Notation::Udim), Ok(4));
+    assert_eq!(Padding::parse("%04d", Notation::Printf).unwrap().notation(), Notation::Printf);
+
+    assert!(parse("#@", Notation::Hash).is_err());
+    assert!(parse("%4d", Notation::Printf).is_err());
+    assert!(parse("%04x", Notation::Printf).is_err());
+    assert!(parse("%99999999999d", Notation::Printf).is_err());
+    assert!(parse("$G4", Notation::Houdini).is_err());
+    assert!(parse("$F4a", Notation::Houdini).is_err());
+    assert!(parse("<F>", Notation::Maya).is_err());
+    assert!(parse("<udim>", Notation::Udim).is_err());
+    assert!(parse("####", Notation::Printf).is_err());
+  }
+
+  #[test]
+  fn test_parse_error() {
+    let error = |pattern: &str, notation| Padding::parse(pattern, notation).unwrap_err();
+    let invalid = |position, character| PaddingError::InvalidCharacter { position, character };
+    assert_eq!(error("#@", Notation::Hash), invalid(1, '@'));
+    assert_eq!(error("%4d", Notation::Printf), invalid(1, '4'));
+    assert_eq!(error("%04x", Notation::Printf), invalid(3, 'x'));
+    assert_eq!(error("%04dd", Notation::Printf), invalid(4, 'd'));
+    assert_eq!(error("%04", Notation::Printf), PaddingError::Truncated { position: 3 });
+    assert_eq!(error("%099999999999d", Notation::Printf), PaddingError::Overflow { position: 2 });
+    assert_eq!(error("%065d", Notation::Printf), PaddingError::Overflow { position: 2 });
+    assert_eq!(error(&"#".repeat(17), Notation::Streaker), PaddingError::Overflow { position: 16 });
+    assert_eq!(error(&"#".repeat(1 << 20), Notation::Hash), PaddingError::Overflow { position: 64 });
+    assert_eq!(Padding::parse(&"#".repeat(16), Notation::Streaker).map(|p| p.value()), Ok(Padding::MAX_VALUE));
+    assert_eq!(error("$G4", Notation::Houdini), invalid(1, 'G'));
+    assert_eq!(error("$F4é", Notation::Houdini), invalid(3, 'é'));
+    assert_eq!(error("<f", Notation::Maya), PaddingError::Truncated { position: 2 });
+    assert_eq!(error("<f>>", Notation::Maya), invalid(3, '>'));
+    assert_eq!(error("", Notation::Udim), PaddingError::Empty { position: 0 });
+    assert_eq!(error("%4d", Notation::Printf).to_string(), "invalid padding character '4' at position 1");
+  }
+
+  #[test]
+  fn test_render() {
+    let padding = Padding::new(4);
+    assert_eq!(padding.render(Notation::Streaker), "#");
+    assert_eq!(padding.render(Notation::Hash), "####");
+    assert_eq!(padding.render(Notation::Printf), "%04d");
+    assert_eq!(padding.render(Notation::Houdini), "$F4");
+    assert_eq!(padding.render(Notation::Maya), "<f>");
+    assert_eq!(padding.render(Notation::Udim), "<UDIM>");
+    assert_eq!(Padding::new(1).render(Notation::Printf), "%d");
+    assert_eq!(Padding::new(0).render(Notation::Houdini), "$F");
+    assert_eq!(Padding::new(0).render(Notation::Hash), "#");
+  }
+
+  #[test]
+  fn test_translate() {
+    let padding = Padding::parse("$F4", Notation::Houdini).unwrap();
+    assert_eq!(padding.token(), "$F4");
+    assert_eq!(padding.with_notation(Notation::Hash).token(), "####");
+    let padding = Padding::parse("%03d", Notation::Printf).unwrap();
+    assert_eq!(padding.render(Notation::Streaker), "@@@");
+  }
+
+  #[test]
+  fn test_width() {
+    assert_eq!(Padding::new(4).width(), Width::Exact);
+    assert!(!Padding::new(4).is_ambiguous());
+    assert_eq!(Padding::minimum(4).width(), Width::Minimum);
+    assert!(Padding::minimum(4).is_ambiguous());
+    assert!(!Padding::minimum(1).is_ambiguous());
+    assert_ne!(Padding::new(4), Padding::minimum(4));
+  }
+
+  #[test]
+  fn test_format() {
+    assert_eq!(Padding::new(4).format(10), "0010");
+    assert_eq!(Padding::new(4).format(-10), "-010");
+    assert_eq!(Padding::new(4).format(12345), "12345");
+    assert_eq!(Padding::new(0).format(7), "7");
+    assert_eq!(Padding::new(1).format(-7), "-7");
+  }
+
+  #[test]
+  fn test_format_subframe() {
+    let padding = Padding::new(4);
+    assert_eq!(padding.format_subframe(1025, 2, Decimals::Fixed), "0010.25");
+    assert_eq!(padding.format_subframe(-105, 1, Decimals::Fixed), "-010.5");
+    assert_eq!(padding.format_subframe(-5, 1, Decimals::Fixed), "-000.5");
+    assert_eq!(padding.format_subframe(100100, 2, Decimals::Fixed), "1001.00");
+    assert_eq!(padding.format_subframe(100150, 2, Decimals::Fixed), "1001.50");
+    assert_eq!(padding.format_subframe(100100, 2, Decimals::Trimmed), "1001");
+    assert_eq!(padding.format_subframe(100150, 2, Decimals::Trimmed), "1001.5");
+    assert_eq!(padding.format_subframe(-10, 0, Decimals::Fixed), padding.format(-10));
+  }
+
+  #[cfg(feature = "serde")]
+  #[test]
+  fn test_serde() {
+    let padding = Padding::new(4).with_notation(Notation::Printf);
+    assert_eq!(serde_json::to_string(&padding).unwrap(), r#"{"width":4,"notation":"printf"}"#);
+    assert_eq!(serde_json::from_str::<Padding>(r#"{"width":4,"notation":"printf"}"#).unwrap(), padding);
+    let minimum = Padding::minimum(4);
+    assert_eq!(serde_json::to_string(&minimum).unwrap(), r#"{"width":4,"notation":"streaker","minimum":true}"#);
+    assert_eq!(serde_json::from_str::<Padding>(&serde_json::to_string(&minimum).unwrap()).unwrap(), minimum);
+    assert!(serde_json::from_str::<Padding>(r#"{"width":4,"notation":"Printf"}"#).is_err());
+    assert!(serde_json::from_str::<Padding>(r#"{"width":100000,"notation":"hash"}"#).is_err());
   }
 
   #[test]
@@ -0,0 +1,355 @@
+use std::fmt;
+use std::path::is_separator;
+use crate::frames::Frames;
+use crate::padding::{Notation, Padding, PaddingError};
+use crate::range::FramesError;
+use crate::streak::Streak;
+
+/// Failure to parse a sequence pattern such as `/shots/render.1-100#.exr`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PatternError {
+
+  /// Pattern is empty, or has no file name
+  Empty { position: usize },
+
+  /// File name has no padding, eg. `#` or `%04d`
+  MissingPadding { position: usize },
+
+  /// Padding is malformed, eg. `%4x`
+  InvalidPadding(PaddingError),
+
+  /// Frame range is malformed
+  InvalidFrames(FramesError),
+
+  /// Frames are given both in the file name and after it
+  DuplicateFrames { position: usize },
+}
+
+impl PatternError {
+
+  /// Byte offset of the failure within the parsed pattern
+  pub fn position(&self) -> usize {
+    match self {
+      PatternError::Empty { position } => *position,
+      PatternError::MissingPadding { position } => *position,
+      PatternError::InvalidPadding(error) => error.position(),
+      PatternError::InvalidFrames(error) => error.position(),
+      PatternError::DuplicateFrames { position } => *position,
+    }
+  }
+}
+
+impl fmt::Display for PatternError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      PatternError::Empty { position } =>
+        write!(f, "missing file name at position {}", position),
+      PatternError::MissingPadding { position } =>
+        write!(f, "missing padding in file name at position {}", position),
+      PatternError::InvalidPadding(error) => error.fmt(f),
+      PatternError::InvalidFrames(error) => error.fmt(f),
+      PatternError::DuplicateFrames { position } =>
+        write!(f, "frames given twice at position {}", position),
+    }
+  }
+}
+
+impl std::error::Error for PatternError {
+  fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+    match self {
+      PatternError::InvalidPadding(error) => Some(error),
+      PatternError::InvalidFrames(error) => Some(error),
+      _ => None,
+    }
+  }
+}
+
+/// Characters that may sit between a prefix and the frame number
+fn is_delimiter(c: char) -> bool {
+  matches!(c, '.' | '_' | '-' | ' ')
+}
+
+/// Padding found in a file name, as a byte range and the padding it describes
+struct Token {
+  start: usize,
+  end: usize,
+  padding: Padding,
+}
+
+/// Find the first padding token of a file name, eg. `#`, `%04d`, `$F4`, `<f>` or `<UDIM>`
+fn find_padding(name: &str, offset: usize) -> Result<Option<Token>, PatternError> {
+  for (start, c) in name.char_indices() {
+    let rest = &name[start..];
+    let end = match c {
+      '@' | '#' => start + rest.find(|c| c != '@' && c != '#').unwrap_or(rest.len()),
+      '%' => {
+        // Digits and the character after them, which should be `d`
+        let digits = 1 + rest[1..].find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len() - 1);
+        start + digits + rest[digits..].chars().next().map_or(0, char::len_utf8)
+      }
+      '$' if rest.starts_with("$F") => {
+        start + 2 + rest[2..].find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len() - 2)
+      }
+      '<' if rest.starts_with("<f>") => start + 3,
+      '<' if rest.starts_with("<UDIM>") => start + 6,
+      _ => continue,
+    };
+    let notation = match c {
+      '@' | '#' => Notation::Streaker,
+      '%' => Notation::Printf,
+      '$' => Notation::Houdini,
+      _ if rest.starts_with("<f>") => Notation::Maya,
+      _ => Notation::Udim,
+    };
+    let padding = Padding::parse(&name[start..end], notation)
+      .map_err(|error| PatternError::InvalidPadding(error.shift(offset + start)))?;
+    return Ok(Some(Token { start, end, padding }));
+  }
+  Ok(None)
+}
+
+/// Whether the first number of a frames expression has a decimal point, eg. `10.5-12`,
+/// and the rest of it does not, eg. `-12`
+fn leading_decimal(frames: &str) -> bool {
+  let number = frames.strip_prefix('-').unwrap_or(frames);
+  let integer = number.find(|c: char| !c.is_ascii_digit()).unwrap_or(number.len());
+  match number[integer..].strip_prefix('.') {
+    Some(rest) => !rest.trim_start_matches(|c: char| c.is_ascii_digit()).contains('.'),
+    None => false,
+  }
+}
+
+/// Token of frames written in front of `@` and `#` padding, which `parse`
+/// reads back the same. A leading subframe with no other decimal point after
+/// it would be left in the prefix, eg. `sim.1.5#`, so it is written as a
+/// range of one frame instead, eg. `sim.1.5-1.5#`.
+pub(crate) fn inline_token(frames: &Frames) -> String {
+  let token = crate::traits::Token::token(frames);
+  if !leading_decimal(&token) {
+    return token;
+  }
+  let sign = usize::from(token.starts_with('-'));
+  let end = sign + token[sign..].find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(token.len() - sign);
+  format!("{}-{}", &token[..end], &token)
+}
+
+/// Frames at the end of the text in front of the padding, and where they start.
+/// They start after a delimiter, and the leftmost start that parses wins so that
+/// ranges such as `-10--1,1.5` are read whole. A digit group followed by a dot is
+/// left in the prefix rather than read as a subframe, eg. `shot_010.1001-1100`
+/// is frames `1001-1100` of `shot_010`, unless the rest of the frames have
+/// subframes too, eg. `sim.1001.25-1001.75x0.5`.
+fn inline_frames(before: &str) -> Option<(usize, Frames)> {
+  before
+    .char_indices()
+    .filter(|&(index, _)| index == 0 || before[..index].ends_with(is_delimiter))
+    .map(|(index, _)| (index, &before[index..]))
+    .filter(|(_, frames)| !frames.trim().is_empty() && !leading_decimal(frames))
+    .find_map(|(index, frames)| frames.parse::<Frames>().ok().map(|frames| (index, frames)))
+}
+
+/// Parse a sequence pattern, eg. `/shots/render.1-100#.exr`,
+/// `render.%04d.exr 1-100`, `render.#.exr` or `render.####.exr`
+pub(crate) fn parse(pattern: &str) -> Result<Streak, PatternError> {
+
+  // Frames listed after the path, eg. `render.%04d.exr 1-100`
+  let mut path = pattern;
+  let mut frames = None;
+  if let Some((head, tail)) = pattern.rsplit_once(char::is_whitespace) {
+    let start = pattern.len() - tail.len();
+    let head = head.trim_end();
+    let numeric = tail.starts_with(|c: char| c.is_ascii_digit() || c == '-');
+    if numeric && find_padding(head, 0).ok().flatten().is_some() {
+      frames = Some(tail.parse::<Frames>().map_err(|e| PatternError::InvalidFrames(e.shift(start)))?);
+      path = head;
+    }
+  }
+
+  let (directory, name, offset) = match path.rfind(is_separator) {
+    Some(0) => (&path[..1], &path[1..], 1),
+    Some(index) => (&path[..index], &path[index + 1..], index + 1),
+    None => ("", path, 0),
+  };
+  if name.is_empty() {
+    return Err(PatternError::Empty { position: offset });
+  }
+
+  let token = find_padding(name, offset)?
+    .ok_or(PatternError::MissingPadding { position: offset })?;
+  let before = &name[..token.start];
+
+  // Frames written in front of `@` and `#` padding, eg. `render.1-100#.exr`
+  let mut stem = before.len();
+  if token.padding.notation() == Notation::Streaker {
+    if let Some((index, inline)) = inline_frames(before) {
+      if frames.is_some() {
+        return Err(PatternError::DuplicateFrames { position: offset + index });
+      }
+      frames = Some(inline);
+      stem = index;
+    }
+  }
+
+  // Without frames in front of it, a run of `#` is Nuke padding, eg. `render.####.exr`
+  let mut padding = token.padding;
+  let text = &name[token.start..token.end];
+  let hashes = padding.notation() == Notation::Streaker && text.len() > 1 && !text.contains('@');
+  if hashes && stem == before.len() {
+    padding = Padding::parse(text, Notation::Hash)
+      .map_err(|error| PatternError::InvalidPadding(error.shift(offset + token.start)))?;
+  }
+
+  let prefix = &before[..stem];
+  let separator = match prefix.chars().next_back() {
+    Some(c) if is_delimiter(c) => c.len_utf8(),
+    _ => 0,
+  };
+  let streak = Streak::new(&prefix[..prefix.len() - separator],
+                           &prefix[prefix.len() - separator..],
+                           padding,
+                           frames.unwrap_or_else(|| Frames::new(&[])),
+                           &name[token.end..]);
+  Ok(streak.with_directory(directory))
+}
+
+#[cfg(test)]
+mod tests {
+
+  use super::*;
+  use std::path::Path;
+  use crate::frames::Frame;
+  use crate::traits::Token;
+
+  fn parts(streak: &Streak) -> (&Path, &str, &str, String, &str) {
+    (streak.directory(), streak.prefix(), streak.separator(), streak.frames().token(), streak.suffix())
+  }
+
+  #[test]
+  fn test_parse_inline() {
+    let streak = parse("/path/render.1-100#.exr").unwrap();
+    assert_eq!(parts(&streak), (Path::new("/path"), "render", ".", "1-100".to_owned(), ".exr"));
+    assert_eq!(streak.padding(), &Padding::new(4));
+    assert_eq!(streak.to_string(), "/path/render.1-100#.exr");
+
+    let streak = parse("shot010.1001-1100x2@@@@.exr").unwrap();
+    assert_eq!(parts(&streak), (Path::new(""), "shot010", ".", "1001-1099x2".to_owned(), ".exr"));
+
+    let streak = parse("sim.-10--1,1.5#.bgeo").unwrap();
+    assert_eq!(streak.prefix(), "sim");
+    assert_eq!(streak.frames().count(), 11);
+
+    let streak = parse("1-10#").unwrap();
+    assert_eq!(parts(&streak), (Path::new(""), "", "", "1-10".to_owned(), ""));
+  }
+
+  #[test]
+  fn test_parse_inline_prefix_digits() {
+    let streak = parse("shot_010.1001-1100#.exr").unwrap();
+    assert_eq!(parts(&streak), (Path::new(""), "shot_010", ".", "1001-1100".to_owned(), ".exr"));
+    assert_eq!(streak.frames().count(), 100);
+    assert_eq!(streak.to_string(), "shot_010.1001-1100#.exr");
+    assert_eq!(parse("render_2.1-10#.exr").unwrap().prefix(), "render_2");
+    assert_eq!(parse("plate-3.1001-1100#.exr").unwrap().prefix(), "plate-3");
+
+    let streak = parse("sim.1001.25-1001.75x0.5#.bgeo").unwrap();
+    assert_eq!(parts(&streak), (Path::new(""), "sim", ".", "1001.25-1001.75x0.5".to_owned(), ".bgeo"));
+    assert_eq!(parse("sim.1001-1002x0.25#.bgeo").unwrap().frames().count(), 5);
+  }
+
+  #[test]
+  fn test_parse_hash() {
+    let streak = parse("render.####.exr").unwrap();
+    assert_eq!(streak.padding(), &Padding::new(4).with_notation(Notation::Hash));
+    assert_eq!(streak.to_string(), "render.####.exr");
+
+    let streak = parse("render.####.exr 1-3").unwrap();
+    assert_eq!(streak.padding().value(), 4);
+    assert_eq!(streak.to_string(), "render.####.exr 1-3");
+    assert_eq!(streak.path_for(2), Path::new("render.0002.exr"));
+
+    assert_eq!(parse("render.1-3##.exr").unwrap().padding(), &Padding::new(8));
+    assert_eq!(parse("render.@#.exr").unwrap().padding(), &Padding::new(5));
+  }
+
+  #[test]
+  fn test_parse_template() {
+    let streak = parse("render.%04d.exr 1-100").unwrap();
+    assert_eq!(parts(&streak), (Path::new(""), "render", ".", "1-100".to_owned(), ".exr"));
+    assert_eq!(streak.padding(), &Padding::new(4).with_notation(Notation::Printf));
+    assert_eq!(streak.to_string(), "render.%04d.exr 1-100");
+
+    let streak = parse("/my renders/render_$F4.exr 1,3").unwrap();
+    assert_eq!(parts(&streak), (Path::new("/my renders"), "render", "_", "1-3x2".to_owned(), ".exr"));
+    assert_eq!(streak.padding().notation(), Notation::Houdini);
+
+    let streak = parse("texture.<UDIM>.tx").unwrap();
+    assert_eq!(streak.padding(), &Padding::new(4).with_notation(Notation::Udim));
+    assert_eq!(streak.frames().count(), 0);
+
+    let streak = parse("render.%04d.exr\u{a0}1-3").unwrap();
+    assert_eq!(parts(&streak), (Path::new(""), "render", ".", "1-3".to_owned(), ".exr"));
+    assert_eq!(parse("render.%04d.exr\u{3000}1-a").map_err(|e| e.position()), Err(20));
+
+    let streak = parse("render.<f>.exr 1-3").unwrap();
+    assert_eq!(streak.padding().notation(), Notation::Maya);
+  }
+
+  #[test]
+  fn test_parse_padding_only() {
+    let streak = parse("/shots/a/render.#.exr").unwrap();
+    assert_eq!(parts(&streak), (Path::new("/shots/a"), "render", ".", String::new(), ".exr"));
+    assert_eq!(streak.padding(), &Padding::new(4));
+    assert_eq!(parse("render.@@@.exr").unwrap().padding(), &Padding::new(3));
+    assert_eq!(parse("/render.#.exr").unwrap().directory(), Path::new("/"));
+  }
+
+  #[test]
+  fn test_parse_round_trip() {
+    for pattern in ["/path/render.1-100#.exr", "render.%04d.exr 1-100", "a_1,3,5-9@@.tar.gz", "render.#.exr",
+                    "render.####.exr 1-3", "shot_010.1001-1100#.exr"].iter() {
+      assert_eq!(parse(pattern).unwrap().to_string(), *pattern);
+    }
+
+    // Frames starting with a subframe, with no other decimal point to tell it from the prefix
+    let subframes = |units: &[Frame], precision| Streak::new("sim", ".", Padding::new(4),
+                                                             Frames::with_precision(units, precision), ".bgeo");
+    for (streak, written) in [(subframes(&[15], 1), "sim.1.5-1.5#.bgeo"),
+                              (subframes(&[10015, 10030, 10040], 1), "sim.1001.5-1001.5,1003,1004#.bgeo"),
+                              (subframes(&[-15, 10, 20], 1), "sim.-1.5--1.5,1,2#.bgeo")].iter() {
+      assert_eq!(streak.to_string(), *written);
+      let parsed = parse(written).unwrap();
+      assert_eq!(parts(&parsed), parts(streak));
+      assert_eq!(parsed.frames().precision(), streak.frames().precision());
+    }
+
+    // Pseudo random sets of frames and subframes, the same on every run
+    let mut state: u64 = 0x9e37_79b9_7f4a_7c15;
+    let mut next = move |bound: u64| {
+      state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+      (state >> 33) % bound
+    };
+    for _ in 0..2000 {
+      let precision = next(3) as u32;
+      let units: Vec<Frame> = (0..1 + next(5)).map(|_| next(3000) as Frame - 1000).collect();
+      let streak = subframes(&units, precision);
+      let parsed = parse(&streak.to_string()).unwrap();
+      assert_eq!(parts(&parsed), parts(&streak), "{}", streak);
+    }
+  }
+
+  #[test]
+  fn test_parse_error() {
+    assert_eq!(parse(""), Err(PatternError::Empty { position: 0 }));
+    assert_eq!(parse("/shots/"), Err(PatternError::Empty { position: 7 }));
+    assert_eq!(parse("/shots/render.exr"), Err(PatternError::MissingPadding { position: 7 }));
+    assert_eq!(parse("render.%4x.exr"),
+               Err(PatternError::InvalidPadding(PaddingError::InvalidCharacter { position: 8, character: '4' })));
+    assert_eq!(parse("render.%04.exr"),
+               Err(PatternError::InvalidPadding(PaddingError::InvalidCharacter { position: 10, character: '.' })));
+    assert_eq!(parse("render.%04"), Err(PatternError::InvalidPadding(PaddingError::Truncated { position: 10 })));
+    assert_eq!(parse("render.%04d.exr 1-a"),
+               Err(PatternError::InvalidFrames(FramesError::InvalidCharacter { position: 18, character: 'a' })));
+    assert_eq!(parse("render.%04d.exr 10-1").map_err(|e| e.position()), Err(19));
+    assert_eq!(parse("render.1-5#.exr 1-100"), Err(PatternError::DuplicateFrames { position: 7 }));
+  }
+}
@@ -0,0 +1,522 @@
+use std::convert::TryFrom;
+use std::fmt;
+use crate::frames::{Frame, Frames};
+
+/// How a stepped range is filled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stride {
+
+  /// Every nth frame from the start, eg. `1-10x2`
+  Fill,
+
+  /// Every frame except every nth frame from the start, eg. `1-10y3`
+  Inverse,
+}
+
+/// Failure to parse a frame range expression
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FramesError {
+
+  /// Expression, or one of its comma separated chunks, is empty
+  Empty { position: usize },
+
+  /// Unexpected character
+  InvalidCharacter { position: usize, character: char },
+
+  /// Range ends before it starts, eg. `10-1`
+  ReversedRange { position: usize },
+
+  /// Step of zero, eg. `1-10x0`
+  ZeroStep { position: usize },
+
+  /// Number does not fit in a frame, or has too many decimal places
+  Overflow { position: usize },
+
+  /// Expression describes more frames than allowed, eg. `0-2000000000`
+  TooManyFrames { position: usize, budget: u64 },
+}
+
+impl FramesError {
+
+  /// Byte offset of the failure within the parsed expression
+  pub fn position(&self) -> usize {
+    match *self {
+      FramesError::Empty { position } => position,
+      FramesError::InvalidCharacter { position, .. } => position,
+      FramesError::ReversedRange { position } => position,
+      FramesError::ZeroStep { position } => position,
+      FramesError::Overflow { position } => position,
+      FramesError::TooManyFrames { position, .. } => position,
+    }
+  }
+
+  /// Same failure, `offset` bytes further into a larger string
+  pub(crate) fn shift(self, offset: usize) -> Self {
+    match self {
+      FramesError::Empty { position } =>
+        FramesError::Empty { position: position + offset },
+      FramesError::InvalidCharacter { position, character } =>
+        FramesError::InvalidCharacter { position: position + offset, character },
+      FramesError::ReversedRange { position } =>
+        FramesError::ReversedRange { position: position + offset },
+      FramesError::ZeroStep { position } =>
+        FramesError::ZeroStep { position: position + offset },
+      FramesError::Overflow { position } =>
+        FramesError::Overflow { position: position + offset },
+      FramesError::TooManyFrames { position, budget } =>
+        FramesError::TooManyFrames { position: position + offset, budget },
+    }
+  }
+}
+
+impl fmt::Display for FramesError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      FramesError::Empty { position } =>
+        write!(f, "empty frame range at position {}", position),
+      FramesError::InvalidCharacter { position, character } =>
+        write!(f, "invalid character '{}' at position {}", character, position),
+      FramesError::ReversedRange { position } =>
+        write!(f, "range ends before it starts at position {}", position),
+      FramesError::ZeroStep { position } =>
+        write!(f, "step of zero at position {}", position),
+      FramesError::Overflow { position } =>
+        write!(f, "frame number too large at position {}", position),
+      FramesError::TooManyFrames { position, budget } =>
+        write!(f, "more than {} frames at position {}", budget, position),
+    }
+  }
+}
+
+impl std::error::Error for FramesError {}
+
+/// A single chunk of a frame range expression, eg. `1-10x2`.
+///
+/// Subframe ranges such as `1-2x0.25` are stored in units of `10^-precision`
+/// frames, so `1-2x0.25` has a precision of 2 and runs from 100 to 200 in
+/// steps of 25. Integer ranges have a precision of 0 and a unit is a frame.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Range {
+  start: Frame,
+  stop: Frame,
+  step: u32,
+  stride: Stride,
+  precision: u32,
+}
+
+/// Write `units` of `10^-precision` frames as a decimal number with exactly
+/// `precision` places, eg. `1001.50` or `1002.00`
+pub(crate) fn fixed(units: i64, precision: u32) -> String {
+  if precision == 0 {
+    return units.to_string();
+  }
+  let scale = 10u64.pow(precision);
+  let sign = if units < 0 { "-" } else { "" };
+  let units = units.unsigned_abs();
+  format!("{}{}.{:0places$}", sign, units / scale, units % scale, places = precision as usize)
+}
+
+/// Write `units` of `10^-precision` frames as a decimal number without
+/// trailing zeros, eg. `1001.25` or `1001`
+pub(crate) fn decimal(units: i64, precision: u32) -> String {
+  let text = fixed(units, precision);
+  match precision {
+    0 => text,
+    _ => text.trim_end_matches('0').trim_end_matches('.').to_owned(),
+  }
+}
+
+impl Range {
+
+  /// Constructor, `stop` is inclusive. Ranges outside the crate come from
+  /// `Range::parse` or `Frames`, which never build an empty or unstepped one.
+  pub(crate) fn new(start: Frame, stop: Frame, step: u32, stride: Stride) -> Self {
+    debug_assert!(step > 0 && start <= stop, "invalid range {}-{}x{}", start, stop, step);
+    Range { start, stop, step, stride, precision: 0 }
+  }
+
+  /// Same range, reading its numbers as units of `10^-precision` frames
+  ///
+  /// # Panics
+  ///
+  /// If `precision` is more than `Frames::MAX_PRECISION`.
+  pub fn with_precision(mut self, precision: u32) -> Self {
+    assert!(precision <= Frames::MAX_PRECISION, "precision of {} is too fine", precision);
+    self.precision = precision;
+    self
+  }
+
+  /// Same range in units of a finer precision, `None` if it does not fit
+  pub fn rescale(&self, precision: u32) -> Option<Self> {
+    if precision > Frames::MAX_PRECISION {
+      return None;
+    }
+    let scale = 10u32.checked_pow(precision.checked_sub(self.precision)?)?;
+    Some(Range {
+      start: self.start.checked_mul(Frame::try_from(scale).ok()?)?,
+      stop: self.stop.checked_mul(Frame::try_from(scale).ok()?)?,
+      step: self.step.checked_mul(scale)?,
+      stride: self.stride,
+      precision,
+    })
+  }
+
+  /// Parse a single chunk, eg. `3`, `1-10`, `1-10x2`, `1-10y3`, `-10--1`
+  /// or `1-2x0.25`. `offset` is the position of `chunk` within the whole
+  /// expression and is only used for error reporting.
+  pub fn parse(chunk: &str, offset: usize) -> Result<Self, FramesError> {
+    let mut cursor = Cursor { text: chunk, index: 0, offset };
+    if chunk.is_empty() {
+      return Err(FramesError::Empty { position: offset });
+    }
+
+    let start = cursor.decimal(true)?;
+    let mut stop = None;
+    let mut step = None;
+    let mut stride = Stride::Fill;
+    if !cursor.is_done() {
+      cursor.expect('-')?;
+      stop = Some(cursor.decimal(true)?);
+      if !cursor.is_done() {
+        stride = match cursor.peek() {
+          Some('x') => Stride::Fill,
+          Some('y') => Stride::Inverse,
+          _ => return Err(cursor.invalid()),
+        };
+        cursor.bump();
+        step = Some(cursor.decimal(false)?);
+        if !cursor.is_done() {
+          return Err(cursor.invalid());
+        }
+      }
+    }
+
+    let precision = [Some(&start), stop.as_ref(), step.as_ref()]
+      .iter()
+      .flatten()
+      .map(|number| number.places)
+      .max()
+      .unwrap_or(0);
+    let start_units = start.units(precision)?;
+    let stop_units = match &stop {
+      Some(stop) => stop.units(precision)?,
+      None => start_units,
+    };
+    if stop_units < start_units {
+      return Err(FramesError::ReversedRange { position: stop.unwrap().position });
+    }
+    let step_units = match &step {
+      Some(step) => {
+        let units = step.units(precision)?;
+        if units == 0 {
+          return Err(FramesError::ZeroStep { position: step.position });
+        }
+        u32::try_from(units).map_err(|_| FramesError::Overflow { position: step.position })?
+      }
+      None => 10u32.pow(precision),
+    };
+    Ok(Range::new(start_units, stop_units, step_units, stride).with_precision(precision))
+  }
+
+  pub fn start(&self) -> Frame {
+    self.start
+  }
+
+  pub fn stop(&self) -> Frame {
+    self.stop
+  }
+
+  pub fn step(&self) -> u32 {
+    self.step
+  }
+
+  pub fn stride(&self) -> Stride {
+    self.stride
+  }
+
+  /// Number of decimal places, `0` for integer frames
+  pub fn precision(&self) -> u32 {
+    self.precision
+  }
+
+  /// Number of frames this range describes, without visiting them
+  pub fn count(&self) -> u64 {
+    let span = u64::from(self.stop.abs_diff(self.start));
+    let filled = span / u64::from(self.step.max(1)) + 1;
+    match self.stride {
+      Stride::Fill => filled,
+      Stride::Inverse => span + 1 - filled,
+    }
+  }
+
+  /// Iterate over every frame described by this range, in units
+  pub fn frames(&self) -> impl Iterator<Item = Frame> + '_ {
+    (self.start..=self.stop)
+      .step_by(match self.stride {
+        Stride::Fill => self.step as usize,
+        Stride::Inverse => 1,
+      })
+      .filter(move |frame| match self.stride {
+        Stride::Fill => true,
+        Stride::Inverse => !frame.abs_diff(self.start).is_multiple_of(self.step),
+      })
+  }
+}
+
+impl fmt::Display for Range {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let start = decimal(self.start.into(), self.precision);
+    let stop = decimal(self.stop.into(), self.precision);
+    if self.start == self.stop {
+      write!(f, "{}", start)
+    } else if self.step == 10u32.pow(self.precision) && self.stride == Stride::Fill {
+      write!(f, "{}-{}", start, stop)
+    } else {
+      let body = match self.stride {
+        Stride::Fill => 'x',
+        Stride::Inverse => 'y',
+      };
+      let step = decimal(self.step.into(), self.precision);
+      write!(f, "{}-{}{}{}", start, stop, body, step)
+    }
+  }
+}
+
+/// A number as written in an expression, eg. `-1001.25`
+struct Decimal {
+  mantissa: i64,
+  places: u32,
+  position: usize,
+}
+
+impl Decimal {
+
+  /// Value in units of `10^-precision`
+  fn units(&self, precision: u32) -> Result<Frame, FramesError> {
+    10i64.checked_pow(precision - self.places)
+      .and_then(|scale| self.mantissa.checked_mul(scale))
+      .and_then(|units| Frame::try_from(units).ok())
+      .ok_or(FramesError::Overflow { position: self.position })
+  }
+}
+
+/// Walks a chunk character by character, tracking the byte position
+struct Cursor<'a> {
+  text: &'a str,
+  index: usize,
+  offset: usize,
+}
+
+impl<'a> Cursor<'a> {
+
+  fn position(&self) -> usize {
+    self.offset + self.index
+  }
+
+  fn peek(&self) -> Option<char> {
+    self.text[self.index..].chars().next()
+  }
+
+  fn bump(&mut self) {
+    if let Some(c) = self.peek() {
+      self.index += c.len_utf8();
+    }
+  }
+
+  fn is_done(&self) -> bool {
+    self.index >= self.text.len()
+  }
+
+  fn invalid(&self) -> FramesError {
+    match self.peek() {
+      Some(character) => FramesError::InvalidCharacter { position: self.position(), character },
+      None => FramesError::Empty { position: self.position() },
+    }
+  }
+
+  fn expect(&mut self, expected: char) -> Result<(), FramesError> {
+    if self.peek() == Some(expected) {
+      self.bump();
+      Ok(())
+    } else {
+      Err(self.invalid())
+    }
+  }
+
+  /// Digits, without a sign
+  fn digits(&mut self) -> Result<&'a str, FramesError> {
+    let begin = self.index;
+    while let Some('0'..='9') = self.peek() {
+      self.bump();
+    }
+    if begin == self.index {
+      return Err(self.invalid());
+    }
+    Ok(&self.text[begin..self.index])
+  }
+
+  /// Number with optional decimal places, negative only if `signed`
+  fn decimal(&mut self, signed: bool) -> Result<Decimal, FramesError> {
+    let begin = self.index;
+    let negative = signed && self.peek() == Some('-');
+    if negative {
+      self.bump();
+    }
+    let position = self.offset + begin;
+    let mut mantissa = self.digits()?.to_owned();
+    let mut places = 0;
+    if self.peek() == Some('.') {
+      self.bump();
+      let fraction = self.digits()?;
+      places = u32::try_from(fraction.len())
+        .ok()
+        .filter(|&places| places <= Frames::MAX_PRECISION)
+        .ok_or(FramesError::Overflow { position })?;
+      mantissa.push_str(fraction);
+    }
+    let mantissa = mantissa
+      .parse::<i64>()
+      .map_err(|_| FramesError::Overflow { position })?;
+    Ok(Decimal {
+      mantissa: if negative { -mantissa } else { mantissa },
+      places,
+      position,
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+
+  use super::*;
+
+  fn expand(chunk: &str) -> Vec<Frame> {
+    Range::parse(chunk, 0).unwrap().frames().collect()
+  }
+
+  #[test]
+  fn test_parse_frame() {
+    assert_eq!(Range::parse("3", 0), Ok(Range::new(3, 3, 1, Stride::Fill)));
+  }
+
+  #[test]
+  fn test_parse_range() {
+    assert_eq!(Range::parse("1-10", 0), Ok(Range::new(1, 10, 1, Stride::Fill)));
+    assert_eq!(Range::parse("3-3", 0), Ok(Range::new(3, 3, 1, Stride::Fill)));
+  }
+
+  #[test]
+  fn test_parse_step() {
+    assert_eq!(Range::parse("1-10x2", 0), Ok(Range::new(1, 10, 2, Stride::Fill)));
+    assert_eq!(Range::parse("5-15y3", 0), Ok(Range::new(5, 15, 3, Stride::Inverse)));
+  }
+
+  #[test]
+  fn test_parse_negative() {
+    assert_eq!(Range::parse("-3", 0), Ok(Range::new(-3, -3, 1, Stride::Fill)));
+    assert_eq!(Range::parse("-10--1", 0), Ok(Range::new(-10, -1, 1, Stride::Fill)));
+    assert_eq!(Range::parse("-10-10x5", 0), Ok(Range::new(-10, 10, 5, Stride::Fill)));
+    assert_eq!(Range::parse("-1--10", 0), Err(FramesError::ReversedRange { position: 3 }));
+    assert_eq!(Range::parse("1-10x-2", 0),
+               Err(FramesError::InvalidCharacter { position: 5, character: '-' }));
+    assert_eq!(Range::parse("--1", 0),
+               Err(FramesError::InvalidCharacter { position: 1, character: '-' }));
+  }
+
+  #[test]
+  fn test_parse_subframe() {
+    assert_eq!(Range::parse("1.5", 0), Ok(Range::new(15, 15, 10, Stride::Fill).with_precision(1)));
+    assert_eq!(Range::parse("1-2x0.25", 0),
+               Ok(Range::new(100, 200, 25, Stride::Fill).with_precision(2)));
+    assert_eq!(Range::parse("1.5-3", 0), Ok(Range::new(15, 30, 10, Stride::Fill).with_precision(1)));
+    assert_eq!(Range::parse("-0.5-0.5x0.5", 0),
+               Ok(Range::new(-5, 5, 5, Stride::Fill).with_precision(1)));
+    assert_eq!(Range::parse("1-2x0.00", 0), Err(FramesError::ZeroStep { position: 4 }));
+    assert_eq!(Range::parse("1.-2", 0), Err(FramesError::InvalidCharacter { position: 2, character: '-' }));
+    assert_eq!(Range::parse("2.5-2.25", 0), Err(FramesError::ReversedRange { position: 4 }));
+    assert_eq!(Range::parse("1.0000000001", 0), Err(FramesError::Overflow { position: 0 }));
+  }
+
+  #[test]
+  fn test_rescale() {
+    let range = Range::new(1, 2, 1, Stride::Fill);
+    assert_eq!(range.rescale(2), Some(Range::new(100, 200, 100, Stride::Fill).with_precision(2)));
+    assert_eq!(range.rescale(0), Some(range.clone()));
+    assert_eq!(range.with_precision(1).rescale(0), None);
+    assert_eq!(Range::new(Frame::MAX, Frame::MAX, 1, Stride::Fill).rescale(1), None);
+  }
+
+  #[test]
+  fn test_decimal() {
+    assert_eq!(decimal(1001, 0), "1001");
+    assert_eq!(decimal(100125, 2), "1001.25");
+    assert_eq!(decimal(5, 1), "0.5");
+    assert_eq!(decimal(-5, 2), "-0.05");
+    assert_eq!(decimal(-150, 2), "-1.5");
+    assert_eq!(decimal(100100, 2), "1001");
+  }
+
+  #[test]
+  fn test_parse_error() {
+    assert_eq!(Range::parse("", 4), Err(FramesError::Empty { position: 4 }));
+    assert_eq!(Range::parse("a", 0),
+               Err(FramesError::InvalidCharacter { position: 0, character: 'a' }));
+    assert_eq!(Range::parse("1-", 2), Err(FramesError::Empty { position: 4 }));
+    assert_eq!(Range::parse("1-5z2", 0),
+               Err(FramesError::InvalidCharacter { position: 3, character: 'z' }));
+    assert_eq!(Range::parse("1-5x2x", 0),
+               Err(FramesError::InvalidCharacter { position: 5, character: 'x' }));
+    assert_eq!(Range::parse("10-1", 0), Err(FramesError::ReversedRange { position: 3 }));
+    assert_eq!(Range::parse("1-10x0", 0), Err(FramesError::ZeroStep { position: 5 }));
+    assert_eq!(Range::parse("1-99999999999", 0), Err(FramesError::Overflow { position: 2 }));
+    assert_eq!(Range::parse("-99999999999", 0), Err(FramesError::Overflow { position: 0 }));
+    assert_eq!(Range::parse("1-2x0.0000000001", 0), Err(FramesError::Overflow { position: 4 }));
+    assert_eq!(Range::parse("1-2x3000000000", 0), Err(FramesError::Overflow { position: 4 }));
+  }
+
+  #[test]
+  fn test_count() {
+    let count = |chunk: &str| Range::parse(chunk, 0).unwrap().count();
+    assert_eq!(count("3"), 1);
+    assert_eq!(count("1-10"), 10);
+    assert_eq!(count("1-10x3"), 4);
+    assert_eq!(count("1-10y3"), 6);
+    assert_eq!(count("1-2x0.25"), 5);
+    assert_eq!(count("-2147483648-2147483647"), 1 << 32);
+    for chunk in ["1-10x3", "1-10y3", "5-15y4", "-3-3y2"].iter() {
+      assert_eq!(count(chunk), expand(chunk).len() as u64);
+    }
+  }
+
+  #[test]
+  fn test_display_wide_step() {
+    let range = Range::new(Frame::MIN, Frame::MAX, u32::MAX, Stride::Fill);
+    assert_eq!(range.to_string(), "-2147483648-2147483647x4294967295");
+  }
+
+  #[test]
+  fn test_frames() {
+    assert_eq!(expand("3"), vec![3]);
+    assert_eq!(expand("1-5"), vec![1, 2, 3, 4, 5]);
+    assert_eq!(expand("1-10x3"), vec![1, 4, 7, 10]);
+    assert_eq!(expand("1-10x2"), vec![1, 3, 5, 7, 9]);
+    assert_eq!(expand("1-10y3"), vec![2, 3, 5, 6, 8, 9]);
+    assert_eq!(expand("-4-4x4"), vec![-4, 0, 4]);
+    assert_eq!(expand("-3-3y3"), vec![-2, -1, 1, 2]);
+    assert_eq!(expand("1-2x0.25"), vec![100, 125, 150, 175, 200]);
+    assert_eq!(expand("1-2y0.5"), vec![11, 12, 13, 14, 16, 17, 18, 19]);
+  }
+
+  #[test]
+  fn test_display() {
+    assert_eq!(Range::new(3, 3, 1, Stride::Fill).to_string(), "3");
+    assert_eq!(Range::new(1, 10, 1, Stride::Fill).to_string(), "1-10");
+    assert_eq!(Range::new(1, 9, 2, Stride::Fill).to_string(), "1-9x2");
+    assert_eq!(Range::new(1, 10, 3, Stride::Inverse).to_string(), "1-10y3");
+    assert_eq!(Range::new(-10, -1, 1, Stride::Fill).to_string(), "-10--1");
+    assert_eq!(Range::new(100100, 101000, 25, Stride::Fill).with_precision(2).to_string(),
+               "1001-1010x0.25");
+    assert_eq!(Range::new(10, 30, 10, Stride::Fill).with_precision(1).to_string(), "1-3");
+    assert_eq!(Range::new(15, 15, 10, Stride::Fill).with_precision(1).to_string(), "1.5");
+  }
+}
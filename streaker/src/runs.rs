@@ -0,0 +1,610 @@
+use std::convert::TryFrom;
+use std::iter::FusedIterator;
+use crate::frames::Frame;
+
+/// Frames `start`, `start + step`, ... up to `stop`, which is on the step.
+/// A single frame has a step of 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct Run {
+  pub(crate) start: Frame,
+  pub(crate) stop: Frame,
+  pub(crate) step: u32,
+}
+
+impl Run {
+
+  pub(crate) fn single(frame: Frame) -> Self {
+    Run { start: frame, stop: frame, step: 1 }
+  }
+
+  /// Frames from `start` every `step` up to `stop` inclusive, `None` if there are none
+  pub(crate) fn new(start: i64, stop: i64, step: u32) -> Option<Self> {
+    if start > stop || step == 0 {
+      return None;
+    }
+    let stop = stop - (stop - start) % step as i64;
+    let start = Frame::try_from(start).ok()?;
+    let stop = Frame::try_from(stop).ok()?;
+    let step = if start == stop { 1 } else { step };
+    Some(Run { start, stop, step })
+  }
+
+  pub(crate) fn count(&self) -> u64 {
+    self.start.abs_diff(self.stop) as u64 / self.step as u64 + 1
+  }
+
+  pub(crate) fn contains(&self, frame: Frame) -> bool {
+    self.start <= frame && frame <= self.stop && frame.abs_diff(self.start).is_multiple_of(self.step)
+  }
+
+  /// Frames of this run from `lo` to `hi` inclusive
+  fn restrict(&self, lo: i64, hi: i64) -> Option<Run> {
+    let step = self.step as i64;
+    let start = self.start as i64;
+    let first = if lo <= start { start } else { start + (lo - start + step - 1) / step * step };
+    Run::new(first, hi.min(self.stop as i64), self.step)
+  }
+
+  /// Every frame of `other` is also a frame of this run
+  fn covers(&self, other: &Run) -> bool {
+    self.contains(other.start)
+      && self.contains(other.stop)
+      && (other.start == other.stop || other.step.is_multiple_of(self.step))
+  }
+
+  pub(crate) fn frames(&self) -> impl Iterator<Item = Frame> {
+    (self.start..=self.stop).step_by(self.step as usize)
+  }
+
+  /// Frames of this run that fall on `origin + k * step`, as the first, the
+  /// last and the step between them, `None` if there are none
+  fn aligned(&self, origin: Frame, step: u32) -> Option<(i64, i64, i64)> {
+    let (start, stop, own, step) = (self.start as i128, self.stop as i128, self.step as i128, step as i128);
+    let divisor = gcd(own, step);
+    let offset = (origin as i128 - start).rem_euclid(step);
+    if offset % divisor != 0 {
+      return None;
+    }
+
+    // Frame `start + index * own` is on the grid when `index * own = offset` modulo `step`
+    let modulus = step / divisor;
+    let index = offset / divisor * inverse(own / divisor, modulus) % modulus;
+    let first = start + index * own;
+    if first > stop {
+      return None;
+    }
+    let stride = own / divisor * step;
+    let last = first + (stop - first) / stride * stride;
+    Some((first as i64, last as i64, i64::try_from(stride).unwrap_or(i64::MAX)))
+  }
+}
+
+/// Greatest common divisor of two positive numbers
+fn gcd(a: i128, b: i128) -> i128 {
+  if b == 0 { a } else { gcd(b, a % b) }
+}
+
+/// Inverse of `a` modulo `modulus`, which are coprime
+fn inverse(a: i128, modulus: i128) -> i128 {
+  let (mut r0, mut r1) = (a.rem_euclid(modulus), modulus);
+  let (mut x0, mut x1) = (1, 0);
+  while r1 != 0 {
+    let quotient = r0 / r1;
+    (r0, r1) = (r1, r0 - quotient * r1);
+    (x0, x1) = (x1, x0 - quotient * x1);
+  }
+  x0.rem_euclid(modulus)
+}
+
+/// Which frames a set operation keeps
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Operation {
+  Union,
+  Intersection,
+  Difference,
+  SymmetricDifference,
+}
+
+impl Operation {
+  fn keeps(self, left: bool, right: bool) -> bool {
+    match self {
+      Operation::Union => left || right,
+      Operation::Intersection => left && right,
+      Operation::Difference => left && !right,
+      Operation::SymmetricDifference => left != right,
+    }
+  }
+}
+
+/// Ascending frames stored as runs of constant step.
+///
+/// Runs are cut the way reading the frames left to right would: a run
+/// takes the frame after its first to set its step, then every following
+/// frame on that step. That makes the runs of a set unique, so sets can be
+/// compared run by run.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub(crate) struct Runs {
+  runs: Vec<Run>,
+
+  /// Frames across every run, kept up to date by `push`
+  count: u64,
+}
+
+impl Runs {
+
+  /// Runs of frames given in any order, possibly repeated
+  pub(crate) fn from_frames(frames: &[Frame]) -> Self {
+    let mut frames = frames.to_vec();
+    frames.sort_unstable();
+    frames.dedup();
+    let mut runs = Runs::default();
+    for frame in frames {
+      runs.push(Run::single(frame));
+    }
+    runs
+  }
+
+  /// Runs of frames from runs that are in order and apart
+  pub(crate) fn from_runs(runs: &[Run]) -> Self {
+    let mut result = Runs::default();
+    for run in runs {
+      result.push(*run);
+    }
+    result
+  }
+
+  /// Runs of frames from runs in any order, possibly overlapping. Runs are
+  /// appended by start, and only merged with the appended runs they overlap.
+  pub(crate) fn from_unsorted(mut runs: Vec<Run>) -> Self {
+    runs.sort_unstable_by_key(|run| run.start);
+    let mut result = Runs::default();
+    for run in runs {
+      if result.last().is_none_or(|last| last < run.start) {
+        result.push(run);
+        continue;
+      }
+      let index = result.runs.partition_point(|stored| stored.stop < run.start);
+      let overlapped = Runs::from_runs(&result.runs.split_off(index));
+      result.count -= overlapped.count();
+      for merged in overlapped.combine(&Runs::from_runs(&[run]), Operation::Union).runs() {
+        result.push(*merged);
+      }
+    }
+    result
+  }
+
+  pub(crate) fn runs(&self) -> &[Run] {
+    &self.runs
+  }
+
+  pub(crate) fn first(&self) -> Option<Frame> {
+    self.runs.first().map(|run| run.start)
+  }
+
+  pub(crate) fn last(&self) -> Option<Frame> {
+    self.runs.last().map(|run| run.stop)
+  }
+
+  pub(crate) fn count(&self) -> u64 {
+    self.count
+  }
+
+  pub(crate) fn contains(&self, frame: Frame) -> bool {
+    let index = self.runs.partition_point(|run| run.stop < frame);
+    self.runs.get(index).is_some_and(|run| run.contains(frame))
+  }
+
+  pub(crate) fn iter(&self) -> FrameIter<'_> {
+    FrameIter {
+      runs: &self.runs,
+      front: self.first().unwrap_or_default(),
+      back: self.last().unwrap_or_default(),
+      remaining: self.count(),
+    }
+  }
+
+  /// Append `run`, which must start after every frame so far
+  pub(crate) fn push(&mut self, mut run: Run) {
+    debug_assert!(self.last().is_none_or(|last| last < run.start));
+    self.count += run.count();
+    loop {
+      let last = match self.runs.last_mut() {
+        Some(last) => last,
+        None => return self.runs.push(run),
+      };
+      let gap = run.start.abs_diff(last.stop);
+
+      // A lone frame pairs with the next one, a longer run takes it if it is on its step
+      let joins = if last.start == last.stop {
+        last.step = gap;
+        true
+      } else {
+        gap == last.step
+      };
+      if !joins {
+        return self.runs.push(run);
+      }
+      if run.start == run.stop || run.step == last.step {
+        last.stop = run.stop;
+        return;
+      }
+      last.stop = run.start;
+      run.start = run.start.wrapping_add_unsigned(run.step);
+      if run.start == run.stop {
+        run.step = 1;
+      }
+    }
+  }
+
+  /// Smallest distance between two frames, read from the steps of the runs
+  /// and the distances between them. `None` with fewer than two frames.
+  pub(crate) fn min_step(&self) -> Option<u32> {
+    let within = self.runs.iter().filter(|run| run.start != run.stop).map(|run| run.step);
+    let between = self.runs.windows(2).map(|pair| pair[1].start.abs_diff(pair[0].stop));
+    within.chain(between).min()
+  }
+
+  /// Frames every `step` from the first that are absent, up to the last frame
+  /// present on that step. Frames off the step neither fill nor open a hole.
+  ///
+  /// Holes open between runs, and within runs whose frames on the step are
+  /// further apart than it. Only runs at least three steps apart need their
+  /// holes pushed one at a time, as the holes themselves are that many runs.
+  pub(crate) fn holes(&self, step: u32) -> Runs {
+    let mut holes = Runs::default();
+    let origin = match self.first() {
+      Some(origin) => origin,
+      None => return holes,
+    };
+    let mut push = |start: i64, stop: i64, step: i64| {
+      if let Some(run) = u32::try_from(step).ok().and_then(|step| Run::new(start, stop, step)) {
+        holes.push(run);
+      }
+    };
+    let step = step as i64;
+    let mut previous = None;
+    for run in &self.runs {
+      let (first, last, stride) = match run.aligned(origin, step as u32) {
+        Some(aligned) => aligned,
+        None => continue,
+      };
+      if let Some(previous) = previous {
+        push(previous + step, first - step, step);
+      }
+      if first < last && stride == 2 * step {
+        push(first + step, last - step, stride);
+      } else if first < last && stride > step {
+        for frame in (first..last).step_by(stride as usize) {
+          push(frame + step, frame + stride - step, step);
+        }
+      }
+      previous = Some(last);
+    }
+    holes
+  }
+
+  /// Append the frames of `run` from `lo` to `hi`
+  fn push_within(&mut self, run: &Run, lo: i64, hi: i64) {
+    if let Some(run) = run.restrict(lo, hi) {
+      self.push(run);
+    }
+  }
+
+  /// Combine two sets, visiting them a stretch at a time where neither
+  /// starts nor stops a run. Within a stretch runs are combined whole when
+  /// one covers the other, and frame by frame otherwise.
+  pub(crate) fn combine(&self, other: &Runs, operation: Operation) -> Runs {
+    let mut result = Runs::default();
+    let (mut left, mut right) = (self.runs.iter().peekable(), other.runs.iter().peekable());
+    let mut position = i64::MIN;
+    loop {
+      let (a, b) = (left.peek().copied(), right.peek().copied());
+      let lo = match (a, b) {
+        (None, None) => break,
+        (Some(a), None) => position.max(a.start as i64),
+        (None, Some(b)) => position.max(b.start as i64),
+        (Some(a), Some(b)) => position.max(a.start.min(b.start) as i64),
+      };
+      let mut hi = i64::MAX;
+      for run in [a, b].iter().flatten() {
+        hi = hi.min(if run.start as i64 <= lo { run.stop as i64 } else { run.start as i64 - 1 });
+      }
+      let active = |run: Option<&Run>| run.filter(|run| run.start as i64 <= lo).and_then(|run| run.restrict(lo, hi));
+      let (a, b) = (active(a), active(b));
+
+      match (a, b) {
+        (Some(a), None) if operation.keeps(true, false) => result.push(a),
+        (None, Some(b)) if operation.keeps(false, true) => result.push(b),
+        (Some(a), Some(b)) => result.push_overlap(&a, &b, operation),
+        _ => {}
+      }
+
+      position = hi + 1;
+      if left.peek().is_some_and(|run| run.stop as i64 <= hi) {
+        left.next();
+      }
+      if right.peek().is_some_and(|run| run.stop as i64 <= hi) {
+        right.next();
+      }
+    }
+    result
+  }
+
+  /// Append the result of an operation on two runs spanning the same stretch
+  fn push_overlap(&mut self, a: &Run, b: &Run, operation: Operation) {
+    match operation {
+      Operation::Union if a.covers(b) => return self.push(*a),
+      Operation::Union if b.covers(a) => return self.push(*b),
+      Operation::Intersection if a.covers(b) => return self.push(*b),
+      Operation::Intersection if b.covers(a) => return self.push(*a),
+      Operation::Difference if b.covers(a) => return,
+      Operation::Difference if a.covers(b) => return self.push_without(a, b),
+      Operation::SymmetricDifference if a.covers(b) => return self.push_without(a, b),
+      Operation::SymmetricDifference if b.covers(a) => return self.push_without(b, a),
+      _ => {}
+    }
+    let (mut a, mut b) = (a.frames().peekable(), b.frames().peekable());
+    loop {
+      let frame = match (a.peek(), b.peek()) {
+        (None, None) => break,
+        (Some(&x), Some(&y)) => x.min(y),
+        (Some(&x), None) => x,
+        (None, Some(&y)) => y,
+      };
+      let left = a.next_if_eq(&frame).is_some();
+      let right = b.next_if_eq(&frame).is_some();
+      if operation.keeps(left, right) {
+        self.push(Run::single(frame));
+      }
+    }
+  }
+
+  /// Append the frames of `outer` that are not in `inner`, which it covers
+  fn push_without(&mut self, outer: &Run, inner: &Run) {
+    let (start, stop) = (inner.start as i64, inner.stop as i64);
+    let step = outer.step as i64;
+    self.push_within(outer, i64::MIN, start - 1);
+    if inner.step as i64 == 2 * step {
+      if let Some(run) = Run::new(start + step, stop - step, inner.step) {
+        self.push(run);
+      }
+    } else if inner.start != inner.stop {
+      for frame in outer.restrict(start, stop).iter().flat_map(Run::frames) {
+        if !inner.contains(frame) {
+          self.push(Run::single(frame));
+        }
+      }
+    }
+    self.push_within(outer, stop + 1, i64::MAX);
+  }
+}
+
+/// Iterator over the frames of `Frames`, in ascending order, see `Frames::frames`
+#[derive(Debug, Clone)]
+pub struct FrameIter<'a> {
+  runs: &'a [Run],
+  front: Frame,
+  back: Frame,
+  remaining: u64,
+}
+
+impl Iterator for FrameIter<'_> {
+  type Item = Frame;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    if self.remaining == 0 {
+      return None;
+    }
+    self.remaining -= 1;
+    let frame = self.front;
+    let run = self.runs[0];
+    if frame == run.stop {
+      self.runs = &self.runs[1..];
+      self.front = self.runs.first().map_or(frame, |run| run.start);
+    } else {
+      self.front = frame.wrapping_add_unsigned(run.step);
+    }
+    Some(frame)
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    match usize::try_from(self.remaining) {
+      Ok(remaining) => (remaining, Some(remaining)),
+      Err(_) => (usize::MAX, None),
+    }
+  }
+}
+
+impl DoubleEndedIterator for FrameIter<'_> {
+  fn next_back(&mut self) -> Option<Self::Item> {
+    if self.remaining == 0 {
+      return None;
+    }
+    self.remaining -= 1;
+    let frame = self.back;
+    let run = self.runs[self.runs.len() - 1];
+    if frame == run.start {
+      self.runs = &self.runs[..self.runs.len() - 1];
+      self.back = self.runs.last().map_or(frame, |run| run.stop);
+    } else {
+      self.back = frame.wrapping_sub_unsigned(run.step);
+    }
+    Some(frame)
+  }
+}
+
+impl ExactSizeIterator for FrameIter<'_> {}
+
+impl FusedIterator for FrameIter<'_> {}
+
+#[cfg(test)]
+mod tests {
+
+  use super::*;
+  use std::collections::BTreeSet;
+
+  fn runs(frames: &[Frame]) -> Runs {
+    Runs::from_frames(frames)
+  }
+
+  fn triples(runs: &Runs) -> Vec<(Frame, Frame, u32)> {
+    runs.runs().iter().map(|run| (run.start, run.stop, run.step)).collect()
+  }
+
+  /// Pseudo random frame sets, the same on every run
+  fn samples() -> Vec<Vec<Frame>> {
+    let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut next = move |bound: u64| {
+      state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+      (state >> 33) % bound
+    };
+    (0..200)
+      .map(|_| {
+        let mut frames = Vec::new();
+        let mut frame = next(20) as Frame - 10;
+        for _ in 0..next(12) {
+          let (step, count) = (next(4) as Frame + 1, next(6));
+          for _ in 0..count {
+            frames.push(frame);
+            frame += step;
+          }
+          frame += next(5) as Frame;
+        }
+        frames
+      })
+      .collect()
+  }
+
+  #[test]
+  fn test_push() {
+    assert_eq!(triples(&runs(&[])), vec![]);
+    assert_eq!(triples(&runs(&[5])), vec![(5, 5, 1)]);
+    assert_eq!(triples(&runs(&[1, 4])), vec![(1, 4, 3)]);
+    assert_eq!(triples(&runs(&[1, 3, 4, 5])), vec![(1, 3, 2), (4, 5, 1)]);
+    assert_eq!(triples(&runs(&[1, 2, 3, 5, 7, 9])), vec![(1, 3, 1), (5, 9, 2)]);
+    assert_eq!(triples(&runs(&[3, 1, 2, 2])), vec![(1, 3, 1)]);
+    assert_eq!(triples(&runs(&[Frame::MIN, Frame::MAX])), vec![(Frame::MIN, Frame::MAX, u32::MAX)]);
+    let pushed = Runs::from_runs(&[Run::new(1, 9, 2).unwrap(), Run::new(10, 12, 1).unwrap()]);
+    assert_eq!(triples(&pushed), vec![(1, 9, 2), (10, 12, 1)]);
+    let pushed = Runs::from_runs(&[Run::single(0), Run::new(2, 4, 1).unwrap()]);
+    assert_eq!(triples(&pushed), vec![(0, 2, 2), (3, 4, 1)]);
+  }
+
+  #[test]
+  fn test_canonical() {
+    for frames in samples() {
+      let built = runs(&frames);
+      let pushed = Runs::from_runs(built.runs());
+      assert_eq!(built, pushed);
+      let expected: BTreeSet<Frame> = frames.iter().copied().collect();
+      assert_eq!(built.iter().collect::<Vec<_>>(), expected.iter().copied().collect::<Vec<_>>());
+      assert_eq!(built.count(), expected.len() as u64);
+      for frame in -20..120 {
+        assert_eq!(built.contains(frame), expected.contains(&frame));
+      }
+    }
+  }
+
+  #[test]
+  fn test_from_unsorted() {
+    for pair in samples().windows(2) {
+      let mut pieces: Vec<Run> = runs(&pair[0]).runs().to_vec();
+      pieces.extend(runs(&pair[1]).runs());
+      pieces.reverse();
+      let expected: Vec<Frame> = pair[0].iter().chain(pair[1].iter()).copied().collect();
+      let built = Runs::from_unsorted(pieces);
+      assert_eq!(built, runs(&expected), "{:?} {:?}", pair[0], pair[1]);
+      assert_eq!(built.count(), runs(&expected).count());
+    }
+    let wide = Runs::from_unsorted(vec![Run::new(5, 9, 1).unwrap(), Run::new(0, 100, 2).unwrap(), Run::single(7)]);
+    assert_eq!(triples(&wide), vec![(0, 4, 2), (5, 10, 1), (12, 100, 2)]);
+  }
+
+  #[test]
+  fn test_combine() {
+    let samples = samples();
+    for pair in samples.windows(2) {
+      let (a, b) = (runs(&pair[0]), runs(&pair[1]));
+      let (x, y): (BTreeSet<Frame>, BTreeSet<Frame>) =
+        (pair[0].iter().copied().collect(), pair[1].iter().copied().collect());
+      let cases = [
+        (Operation::Union, &x | &y),
+        (Operation::Intersection, &x & &y),
+        (Operation::Difference, &x - &y),
+        (Operation::SymmetricDifference, &x ^ &y),
+      ];
+      for (operation, expected) in cases.iter() {
+        let expected: Vec<Frame> = expected.iter().copied().collect();
+        let combined = a.combine(&b, *operation);
+        assert_eq!(combined, runs(&expected), "{:?} {:?} {:?}", operation, pair[0], pair[1]);
+        assert_eq!(combined.count(), expected.len() as u64);
+      }
+    }
+  }
+
+  #[test]
+  fn test_combine_runs() {
+    let all = Runs::from_runs(&[Run::new(1, 1_000_000, 1).unwrap()]);
+    let odd = Runs::from_runs(&[Run::new(1, 1_000_000, 2).unwrap()]);
+    assert_eq!(triples(&all.combine(&odd, Operation::Difference)), vec![(2, 1_000_000, 2)]);
+    assert_eq!(triples(&all.combine(&odd, Operation::Intersection)), vec![(1, 999_999, 2)]);
+    assert_eq!(triples(&odd.combine(&all, Operation::Union)), vec![(1, 1_000_000, 1)]);
+    let hole = runs(&[500_000]);
+    assert_eq!(triples(&all.combine(&hole, Operation::Difference)),
+               vec![(1, 499_999, 1), (500_001, 1_000_000, 1)]);
+  }
+
+  #[test]
+  fn test_holes() {
+    for frames in samples() {
+      for step in 1..6 {
+        let origin = frames.iter().min().copied().unwrap_or_default();
+        let aligned: BTreeSet<Frame> = frames.iter().copied().filter(|frame| (frame - origin) % step == 0).collect();
+        let mut expected = Vec::new();
+        for (previous, next) in aligned.iter().zip(aligned.iter().skip(1)) {
+          expected.extend((previous + step..*next).step_by(step as usize));
+        }
+        assert_eq!(runs(&frames).holes(step as u32), runs(&expected), "{:?} {}", frames, step);
+      }
+    }
+  }
+
+  #[test]
+  fn test_holes_runs() {
+    let odd = Runs::from_runs(&[Run::new(1, 3_000_001, 2).unwrap()]);
+    assert_eq!(triples(&odd.holes(1)), vec![(2, 3_000_000, 2)]);
+    assert!(odd.holes(2).runs().is_empty());
+    assert_eq!(odd.holes(3).count(), 500_000);
+    let apart = Runs::from_runs(&[Run::new(0, 10, 1).unwrap(), Run::new(1_000_000, 2_000_000, 5).unwrap()]);
+    assert_eq!(triples(&apart.holes(5)), vec![(15, 999_995, 5)]);
+    assert_eq!(triples(&runs(&[Frame::MIN, Frame::MAX]).holes(1)), vec![(Frame::MIN + 1, Frame::MAX - 1, 1)]);
+  }
+
+  #[test]
+  fn test_min_step() {
+    assert_eq!(runs(&[]).min_step(), None);
+    assert_eq!(runs(&[7]).min_step(), None);
+    assert_eq!(runs(&[1, 5, 7]).min_step(), Some(2));
+    assert_eq!(runs(&[1, 3, 5, 7, 20, 30, 40, 41]).min_step(), Some(1));
+    assert_eq!(Runs::from_runs(&[Run::new(0, 1_000_000, 4).unwrap()]).min_step(), Some(4));
+    for frames in samples() {
+      let expected = frames.windows(2).map(|pair| pair[0].abs_diff(pair[1])).filter(|&step| step > 0).min();
+      assert_eq!(runs(&frames).min_step(), expected, "{:?}", frames);
+    }
+  }
+
+  #[test]
+  fn test_iter() {
+    let frames = runs(&[1, 2, 3, 10, 20, 30, 31]);
+    let mut iter = frames.iter();
+    assert_eq!(iter.len(), 7);
+    assert_eq!(iter.next(), Some(1));
+    assert_eq!(iter.next_back(), Some(31));
+    assert_eq!(iter.next_back(), Some(30));
+    assert_eq!(iter.collect::<Vec<_>>(), vec![2, 3, 10, 20]);
+    assert_eq!(runs(&[]).iter().next(), None);
+    let far = runs(&[Frame::MIN, Frame::MAX]);
+    assert_eq!(far.iter().collect::<Vec<_>>(), vec![Frame::MIN, Frame::MAX]);
+    assert_eq!(far.iter().rev().collect::<Vec<_>>(), vec![Frame::MAX, Frame::MIN]);
+  }
+}
@@ -0,0 +1,478 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use crate::frames::{Frame, Frames};
+use crate::grammar::Grammar;
+use crate::padding::{Decimals, Padding};
+use crate::streak::Streak;
+
+/// Files of a single directory, grouped into sequences
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Scan {
+  directory: PathBuf,
+  streaks: Vec<Streak>,
+  singles: Vec<PathBuf>,
+  ambiguous: Vec<PathBuf>,
+}
+
+/// Files sharing everything but their frame number may belong to the same sequence
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+struct Key {
+  prefix: String,
+  separator: String,
+  suffix: String,
+}
+
+/// A frame number read from a file name
+struct Number {
+  units: Frame,
+  precision: u32,
+
+  /// Characters before any decimal point, including the sign
+  width: usize,
+
+  /// Written with leading zeros, which pins the padding to `width`
+  padded: bool,
+
+  /// Written with trailing zeros after the decimal point, eg. `1001.50`,
+  /// which pins the decimal places to `precision`
+  trailing: bool,
+}
+
+impl Number {
+
+  /// Same number in units of `10^-precision` frames, `None` if it overflows
+  fn rescale(self, precision: u32) -> Option<Number> {
+    let scale = 10i32.checked_pow(precision.checked_sub(self.precision)?)?;
+    Some(Number { units: self.units.checked_mul(scale)?, precision, ..self })
+  }
+}
+
+/// Read a frame number, eg. `0012.5` is `125` tenths of a frame padded to four
+fn frame(digits: &str) -> Option<Number> {
+  let (integer, units, precision) = match digits.find('.') {
+    None => (digits, digits.parse().ok()?, 0),
+    Some(dot) => {
+      let units = format!("{}{}", &digits[..dot], &digits[dot + 1..]).parse().ok()?;
+      (&digits[..dot], units, (digits.len() - dot - 1) as u32)
+    }
+  };
+  if precision > Frames::MAX_PRECISION || integer.len() > Padding::MAX_VALUE as usize {
+    return None;
+  }
+  let unsigned = integer.strip_prefix('-').unwrap_or(integer);
+  Some(Number {
+    units,
+    precision,
+    width: integer.len(),
+    padded: unsigned.len() > 1 && unsigned.starts_with('0'),
+    trailing: precision > 0 && digits.ends_with('0'),
+  })
+}
+
+/// Numbers of files sharing a name, with the precision and decimal places they are written with
+type Writing = (u32, Decimals, Vec<(Number, OsString)>);
+
+/// Split files of the same name by how the fractions of their frames are written.
+///
+/// Trailing zeros, eg. `1001.50`, pin the decimal places, so numbers with as
+/// many places belong together. Other numbers of different precisions are
+/// written without trailing zeros, as Houdini's `$FF` does, eg. `1001`,
+/// `1001.25` and `1001.5`, and belong together at the finest precision.
+/// Numbers that overflow at that precision are returned on their own.
+fn writings(numbers: Vec<(Number, OsString)>) -> (Vec<Writing>, Vec<OsString>) {
+  let fixed: BTreeSet<u32> = numbers
+    .iter()
+    .filter(|(number, _)| number.trailing)
+    .map(|(number, _)| number.precision)
+    .collect();
+  let mut groups: BTreeMap<u32, Vec<(Number, OsString)>> = BTreeMap::new();
+  let mut rest = Vec::new();
+  for (number, name) in numbers {
+    match fixed.contains(&number.precision) {
+      true => groups.entry(number.precision).or_default().push((number, name)),
+      false => rest.push((number, name)),
+    }
+  }
+  let mut writings: Vec<Writing> = groups
+    .into_iter()
+    .map(|(precision, numbers)| (precision, Decimals::Fixed, numbers))
+    .collect();
+
+  let precisions: BTreeSet<u32> = rest.iter().map(|(number, _)| number.precision).collect();
+  let mut overflow = Vec::new();
+  match precisions.iter().next_back() {
+    None => {}
+    Some(&precision) if precisions.len() == 1 => writings.push((precision, Decimals::Fixed, rest)),
+    Some(&precision) => {
+      let mut numbers = Vec::new();
+      for (number, name) in rest {
+        match number.rescale(precision) {
+          Some(number) => numbers.push((number, name)),
+          None => overflow.push(name),
+        }
+      }
+      writings.push((precision, Decimals::Trimmed, numbers));
+    }
+  }
+  writings.sort_by_key(|(precision, _, _)| *precision);
+  (writings, overflow)
+}
+
+/// Split files of the same name into sequences by padding.
+///
+/// Zero padded frames, eg. `0010`, only belong with frames of the same
+/// width. Frames without leading zeros, eg. `10000`, fit any padding up
+/// to their width: they join the one padded sequence they fit, are
+/// ambiguous when they fit several, and otherwise form an unpadded sequence.
+fn partition(numbers: Vec<(Number, OsString)>) -> (Vec<(Padding, Vec<Frame>)>, Vec<OsString>) {
+  let widths: BTreeSet<usize> = numbers
+    .iter()
+    .filter(|(number, _)| number.padded)
+    .map(|(number, _)| number.width)
+    .collect();
+  let mut padded: BTreeMap<usize, Vec<Frame>> = BTreeMap::new();
+  let mut unpadded: Vec<(Frame, usize)> = Vec::new();
+  let mut ambiguous = Vec::new();
+  for (number, name) in numbers {
+    if number.padded {
+      padded.entry(number.width).or_default().push(number.units);
+      continue;
+    }
+    let mut fits = widths.range(..=number.width);
+    match (fits.next(), fits.next()) {
+      (Some(width), None) => padded.entry(*width).or_default().push(number.units),
+      (Some(_), Some(_)) => ambiguous.push(name),
+      (None, _) => unpadded.push((number.units, number.width)),
+    }
+  }
+
+  let mut sequences: Vec<(Padding, Vec<Frame>)> = padded
+    .into_iter()
+    .map(|(width, frames)| (Padding::new(width as u32), frames))
+    .collect();
+  if let Some(width) = unpadded.iter().map(|(_, width)| *width).min() {
+    let padding = match width {
+      0 | 1 => Padding::new(width as u32),
+      _ => Padding::minimum(width as u32),
+    };
+    sequences.push((padding, unpadded.into_iter().map(|(frame, _)| frame).collect()));
+  }
+  sequences.sort_by_key(|(padding, _)| padding.value());
+  (sequences, ambiguous)
+}
+
+impl Scan {
+
+  /// Group file names found in `directory` into sequences
+  pub fn from_names<P, I>(directory: P, names: I, grammar: &Grammar) -> Self
+    where P: AsRef<Path>,
+          I: IntoIterator<Item = OsString> {
+    let directory = directory.as_ref();
+    let mut groups: BTreeMap<Key, Vec<(Number, OsString)>> = BTreeMap::new();
+    let mut singles = Vec::new();
+    for name in names {
+      let parts = name.to_str()
+        .and_then(|name| grammar.split(name))
+        .and_then(|parts| frame(parts.digits).map(|number| (parts, number)));
+      match parts {
+        Some((parts, number)) => {
+          let key = Key {
+            prefix: parts.prefix.to_owned(),
+            separator: parts.separator.to_owned(),
+            suffix: parts.suffix.to_owned(),
+          };
+          groups.entry(key).or_default().push((number, name.clone()));
+        }
+        None => singles.push(directory.join(&name)),
+      }
+    }
+
+    let mut streaks = Vec::new();
+    let mut ambiguous = Vec::new();
+    for (key, numbers) in groups {
+      let (writings, overflow) = writings(numbers);
+      singles.extend(overflow.iter().map(|name| directory.join(name)));
+      for (precision, decimals, numbers) in writings {
+        let (sequences, unresolved) = partition(numbers);
+        ambiguous.extend(unresolved.iter().map(|name| directory.join(name)));
+        for (padding, frames) in sequences {
+          streaks.push(Streak::new(&key.prefix,
+                                   &key.separator,
+                                   padding,
+                                   Frames::with_precision(&frames, precision),
+                                   &key.suffix)
+            .with_directory(directory)
+            .with_decimals(decimals)
+            .with_grammar(grammar.clone()));
+        }
+      }
+    }
+    singles.sort();
+    ambiguous.sort();
+
+    Scan {
+      directory: directory.to_path_buf(),
+      streaks,
+      singles,
+      ambiguous,
+    }
+  }
+
+  /// Directory that was scanned
+  pub fn directory(&self) -> &Path {
+    &self.directory
+  }
+
+  /// Sequences, ordered by name
+  pub fn streaks(&self) -> &[Streak] {
+    &self.streaks
+  }
+
+  /// Files that are not part of any sequence, ordered by name
+  pub fn singles(&self) -> &[PathBuf] {
+    &self.singles
+  }
+
+  /// Files that fit more than one sequence, eg. `a.1000.exr` next to both
+  /// `a.001.exr` and `a.0001.exr`, ordered by name
+  pub fn ambiguous(&self) -> &[PathBuf] {
+    &self.ambiguous
+  }
+}
+
+/// Contents of a single directory
+pub(crate) struct Listing {
+  pub(crate) files: Vec<OsString>,
+  pub(crate) directories: Vec<PathBuf>,
+}
+
+/// Hidden files and directories start with a dot
+fn is_hidden(name: &OsStr) -> bool {
+  name.to_str().is_some_and(|name| name.starts_with('.'))
+}
+
+/// Read a directory, splitting its entries into files and subdirectories
+pub(crate) fn list(path: &Path, follow_links: bool, hidden: bool) -> io::Result<Listing> {
+  let mut files = Vec::new();
+  let mut directories = Vec::new();
+  for entry in fs::read_dir(path)? {
+    let entry = entry?;
+    let name = entry.file_name();
+    if !hidden && is_hidden(&name) {
+      continue;
+    }
+    let file_type = entry.file_type()?;
+    if file_type.is_dir() {
+      directories.push(entry.path());
+    } else if file_type.is_symlink() && entry.path().is_dir() {
+      if follow_links {
+        directories.push(entry.path());
+      }
+    } else {
+      files.push(name);
+    }
+  }
+  directories.sort();
+  Ok(Listing { files, directories })
+}
+
+/// Group the files of a directory into sequences. Subdirectories are ignored.
+pub fn scan<P: AsRef<Path>>(path: P) -> io::Result<Scan> {
+  scan_with(path, &Grammar::default())
+}
+
+/// Group the files of a directory into sequences, splitting names with `grammar`
+pub fn scan_with<P: AsRef<Path>>(path: P, grammar: &Grammar) -> io::Result<Scan> {
+  let path = path.as_ref();
+  let listing = list(path, false, true)?;
+  Ok(Scan::from_names(path, listing.files, grammar))
+}
+
+#[cfg(test)]
+mod tests {
+
+  use super::*;
+  use crate::fixture::Fixture;
+  use crate::grammar::Rule;
+
+  fn names(names: &[&str]) -> Vec<OsString> {
+    names.iter().map(OsString::from).collect()
+  }
+
+  fn tokens(scan: &Scan) -> Vec<String> {
+    scan.streaks().iter().map(|s| s.file_name()).collect()
+  }
+
+  #[test]
+  fn test_from_names() {
+    let scan = Scan::from_names("/renders", names(&[
+      "render.1003.exr",
+      "render.1001.exr",
+      "render.1002.exr",
+      "render.1002.jpg",
+      "notes.txt",
+      "comp.0010.exr",
+    ]), &Grammar::default());
+    assert_eq!(scan.directory(), Path::new("/renders"));
+    assert_eq!(tokens(&scan), vec!["comp.10#.exr", "render.1001-1003#.exr", "render.1002#.jpg"]);
+    assert_eq!(scan.streaks()[0].directory(), Path::new("/renders"));
+    assert_eq!(scan.singles(), &[PathBuf::from("/renders/notes.txt")]);
+  }
+
+  #[test]
+  fn test_from_names_width() {
+    let scan = Scan::from_names("", names(&["a.001.exr", "a.0001.exr", "a.0002.exr"]), &Grammar::default());
+    assert_eq!(tokens(&scan), vec!["a.1@@@.exr", "a.1-2#.exr"]);
+  }
+
+  #[test]
+  fn test_from_names_negative() {
+    let scan = Scan::from_names("", names(&[
+      "sim.-010.bgeo",
+      "sim.-001.bgeo",
+      "sim.0000.bgeo",
+      "sim.0001.bgeo",
+      "sim.-0010.bgeo",
+    ]), &Grammar::default());
+    assert_eq!(tokens(&scan), vec!["sim.-10,-1-1#.bgeo", "sim.-10@#.bgeo"]);
+    assert_eq!(scan.streaks()[0].frames().first(), Some(-10));
+  }
+
+  #[test]
+  fn test_from_names_subframes() {
+    let files = names(&[
+      "sim.1001.00.bgeo",
+      "sim.1001.25.bgeo",
+      "sim.1001.50.bgeo",
+      "sim.1001.75.bgeo",
+      "sim.1002.00.bgeo",
+      "cache.0012.5.abc",
+      "cache.0013.0.abc",
+      "cache.0013.abc",
+    ]);
+    let scan = Scan::from_names("", files, &Grammar::default().subframes(true));
+    assert_eq!(tokens(&scan), vec!["cache.13#.abc", "cache.12.5-13x0.5#.abc", "sim.1001-1002x0.25#.bgeo"]);
+    assert_eq!(scan.streaks()[2].frames().precision(), 2);
+    assert_eq!(scan.streaks()[2].frames().count(), 5);
+  }
+
+  #[test]
+  fn test_from_names_trimmed() {
+    let files = ["sim.1001.bgeo", "sim.1001.25.bgeo", "sim.1001.5.bgeo", "sim.1001.75.bgeo", "sim.1002.bgeo"];
+    let scan = Scan::from_names("", names(&files), &Grammar::default().subframes(true));
+    assert_eq!(tokens(&scan), vec!["sim.1001-1002x0.25#.bgeo"]);
+    assert_eq!(scan.streaks()[0].decimals(), Decimals::Trimmed);
+    let paths: Vec<PathBuf> = scan.streaks()[0].paths().collect();
+    assert_eq!(paths, files.iter().map(PathBuf::from).collect::<Vec<_>>());
+
+    let scan = Scan::from_names("", names(&["a.1.exr", "a.1.000000001.exr", "a.2.5.exr"]), &Grammar::default().subframes(true));
+    assert_eq!(tokens(&scan), vec!["a.1-1.000000001x0.000000001@.exr"]);
+    assert_eq!(scan.singles(), &[PathBuf::from("a.2.5.exr")]);
+  }
+
+  #[test]
+  fn test_from_names_paths() {
+    let files = ["sim.1001.00.bgeo", "sim.1001.50.bgeo", "sim.1002.00.bgeo", "cache.0012.5.abc", "cache.0013.0.abc"];
+    let scan = Scan::from_names("/caches", names(&files), &Grammar::default().subframes(true));
+    let mut paths: Vec<PathBuf> = scan.streaks().iter().flat_map(Streak::paths).collect();
+    let mut expected: Vec<PathBuf> = files.iter().map(|name| Path::new("/caches").join(name)).collect();
+    paths.sort();
+    expected.sort();
+    assert_eq!(paths, expected);
+  }
+
+  #[test]
+  fn test_frame() {
+    let frame = |digits| frame(digits).map(|n| (n.units, n.precision, n.width, n.padded));
+    assert_eq!(frame("1001"), Some((1001, 0, 4, false)));
+    assert_eq!(frame("0001"), Some((1, 0, 4, true)));
+    assert_eq!(frame("0"), Some((0, 0, 1, false)));
+    assert_eq!(frame("-010"), Some((-10, 0, 4, true)));
+    assert_eq!(frame("-10"), Some((-10, 0, 3, false)));
+    assert_eq!(frame("1001.25"), Some((100125, 2, 4, false)));
+    assert_eq!(frame("-0012.5"), Some((-125, 1, 5, true)));
+    assert_eq!(frame("99999999999"), None);
+    assert_eq!(frame("0.0000000001"), None);
+    assert_eq!(frame(&format!("{}1", "0".repeat(64))), None);
+  }
+
+  #[test]
+  fn test_from_names_overflow() {
+    let scan = Scan::from_names("", names(&["a.9998.exr", "a.9999.exr", "a.10000.exr", "a.10001.exr"]), &Grammar::default());
+    assert_eq!(tokens(&scan), vec!["a.9998-10001#.exr"]);
+    assert_eq!(scan.streaks()[0].padding(), &Padding::minimum(4));
+    assert!(scan.streaks()[0].padding().is_ambiguous());
+
+    let scan = Scan::from_names("", names(&["a.0998.exr", "a.0999.exr", "a.1000.exr", "a.10000.exr"]), &Grammar::default());
+    assert_eq!(tokens(&scan), vec!["a.998-1000,10000#.exr"]);
+    assert_eq!(scan.streaks()[0].padding(), &Padding::new(4));
+  }
+
+  #[test]
+  fn test_from_names_unpadded() {
+    let scan = Scan::from_names("", names(&["a.1.exr", "a.2.exr", "a.9.exr", "a.10.exr", "a.11.exr"]), &Grammar::default());
+    assert_eq!(tokens(&scan), vec!["a.1,2,9-11@.exr"]);
+    assert!(!scan.streaks()[0].padding().is_ambiguous());
+
+    let scan = Scan::from_names("", names(&["a.1001.exr", "a.1002.exr"]), &Grammar::default());
+    assert_eq!(scan.streaks()[0].padding(), &Padding::minimum(4));
+    assert!(scan.streaks()[0].padding().is_ambiguous());
+  }
+
+  #[test]
+  fn test_from_names_incompatible() {
+    let scan = Scan::from_names("", names(&[
+      "a.5.exr",
+      "a.0010.exr",
+      "a.001.exr",
+      "a.1000.exr",
+      "a.0999.exr",
+      "a.0020.exr",
+    ]), &Grammar::default());
+    assert_eq!(tokens(&scan), vec!["a.5@.exr", "a.1@@@.exr", "a.10,20,999#.exr"]);
+    assert_eq!(scan.ambiguous(), &[PathBuf::from("a.1000.exr")]);
+    assert!(scan.singles().is_empty());
+  }
+
+  #[test]
+  fn test_from_names_grammar() {
+    let files = names(&[
+      "render_1001.exr",
+      "render_1002.exr",
+      "render.1001.exr",
+      "plate.1001.tar.gz",
+      "plate.1002.tar.gz",
+    ]);
+    let scan = Scan::from_names("", files.clone(), &Grammar::default());
+    assert_eq!(tokens(&scan), vec!["plate.1001-1002#.tar.gz", "render.1001#.exr"]);
+    assert_eq!(scan.singles().len(), 2);
+
+    let grammar = Grammar::new(Rule::BeforeExtension);
+    let scan = Scan::from_names("", files, &grammar);
+    assert_eq!(tokens(&scan), vec!["plate.1001-1002#.tar.gz", "render.1001#.exr", "render_1001-1002#.exr"]);
+    assert!(scan.singles().is_empty());
+    assert_eq!(scan.streaks()[2].grammar(), &grammar);
+    assert_eq!(scan.streaks()[2].separator(), "_");
+  }
+
+  #[test]
+  fn test_scan() {
+    let fixture = Fixture::new("scan", &[
+      "image.1001.txt",
+      "image.1002.txt",
+      "image.1004.txt",
+      "other.0010.txt",
+      "readme.txt",
+      "nested/image.1003.txt",
+    ]);
+    let scan = scan(fixture.path()).unwrap();
+    assert_eq!(tokens(&scan), vec!["image.1001,1002,1004#.txt", "other.10#.txt"]);
+    assert_eq!(scan.singles(), &[fixture.path().join("readme.txt")]);
+    assert!(super::scan(fixture.path().join("missing")).is_err());
+    let scan = scan_with(fixture.path(), &Grammar::new(Rule::LastDigits)).unwrap();
+    assert_eq!(tokens(&scan), vec!["image.1001,1002,1004#.txt", "other.10#.txt"]);
+  }
+}
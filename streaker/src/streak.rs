@@ -1,7 +1,349 @@
-use crate::frames::Frames;
-use crate::padding::Padding;
+use std::convert::TryFrom;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use crate::frames::{Frame, Frames};
+use crate::runs::FrameIter;
+use crate::grammar::Grammar;
+use crate::padding::{Decimals, Notation, Padding};
+use crate::pattern::{self, PatternError};
+use crate::traits::Token;
 
+/// A sequence of files sharing a name, eg. `render.1-100#.exr`.
+///
+/// With the `serde` feature a sequence serializes as an object of its parts,
+/// eg. `{"directory": "/shots", "prefix": "render", "separator": ".",
+/// "padding": {"width": 4, "notation": "streaker"}, "frames": "1-100",
+/// "suffix": ".exr", "decimals": "fixed"}`. The grammar it was matched with is left out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Streak {
+  #[cfg_attr(feature = "serde", serde(default))]
+  directory: PathBuf,
+  prefix: String,
+  separator: String,
   padding: Padding,
   frames: Frames,
-}
\ No newline at end of file
+  suffix: String,
+  #[cfg_attr(feature = "serde", serde(default))]
+  decimals: Decimals,
+  #[cfg_attr(feature = "serde", serde(skip))]
+  grammar: Grammar,
+}
+
+impl Streak {
+
+  /// Constructor
+  pub fn new(prefix: &str, separator: &str, padding: Padding, frames: Frames, suffix: &str) -> Self {
+    Streak {
+      directory: PathBuf::new(),
+      prefix: prefix.to_owned(),
+      separator: separator.to_owned(),
+      padding,
+      frames,
+      suffix: suffix.to_owned(),
+      decimals: Decimals::default(),
+      grammar: Grammar::default(),
+    }
+  }
+
+  /// Same sequence, located in `directory`
+  pub fn with_directory<P: AsRef<Path>>(mut self, directory: P) -> Self {
+    self.directory = directory.as_ref().to_path_buf();
+    self
+  }
+
+  /// Directory containing the sequence, empty if unknown
+  pub fn directory(&self) -> &Path {
+    &self.directory
+  }
+
+  /// Same sequence, writing the fraction of subframes with `decimals`
+  pub fn with_decimals(mut self, decimals: Decimals) -> Self {
+    self.decimals = decimals;
+    self
+  }
+
+  /// How the fraction of subframes is written in file names, eg. `1001.50`
+  /// with fixed decimals at a precision of 2, or `1001.5` trimmed
+  pub fn decimals(&self) -> Decimals {
+    self.decimals
+  }
+
+  /// Same sequence, recording the grammar its files were matched with
+  pub fn with_grammar(mut self, grammar: Grammar) -> Self {
+    self.grammar = grammar;
+    self
+  }
+
+  /// Grammar used to split file names into prefix, frame and suffix
+  pub fn grammar(&self) -> &Grammar {
+    &self.grammar
+  }
+
+  /// Sequence name without its directory, eg. `render.1-100#.exr`.
+  /// Only `@` and `#` padding is written after the frames, other
+  /// notations leave them out, eg. `render.%04d.exr`.
+  pub fn file_name(&self) -> String {
+    let frames = match self.padding.notation() {
+      Notation::Streaker => pattern::inline_token(&self.frames),
+      _ => String::new(),
+    };
+    format!("{}{}{}{}{}",
+            self.prefix,
+            self.separator,
+            frames,
+            self.padding.token(),
+            self.suffix)
+  }
+
+  /// Path of a single frame, given in the units of `frames()`, eg. `render.0042.exr`,
+  /// or `sim.1001.50.bgeo` for a subframe at a precision of 2
+  pub fn path_for(&self, frame: Frame) -> PathBuf {
+    let name = format!("{}{}{}{}",
+                       self.prefix,
+                       self.separator,
+                       self.padding.format_subframe(frame, self.frames.precision(), self.decimals),
+                       self.suffix);
+    self.directory.join(name)
+  }
+
+  /// Path of every frame in order, built as they are iterated
+  pub fn paths(&self) -> Paths<'_> {
+    Paths { streak: self, frames: self.frames.frames() }
+  }
+
+  /// Name before the frame number, eg. `render`
+  pub fn prefix(&self) -> &str {
+    &self.prefix
+  }
+
+  /// Characters between the prefix and the frame number, eg. `.`
+  pub fn separator(&self) -> &str {
+    &self.separator
+  }
+
+  pub fn padding(&self) -> &Padding {
+    &self.padding
+  }
+
+  pub fn set_padding(&mut self, padding: Padding) {
+    self.padding = padding;
+  }
+
+  pub fn frames(&self) -> &Frames {
+    &self.frames
+  }
+
+  pub fn frames_mut(&mut self) -> &mut Frames {
+    &mut self.frames
+  }
+
+  /// Everything after the frame number, eg. `.exr`
+  pub fn suffix(&self) -> &str {
+    &self.suffix
+  }
+
+  /// File extension without its leading dot, eg. `exr` or `tar.gz`
+  pub fn extension(&self) -> Option<&str> {
+    self.suffix
+      .find('.')
+      .map(|index| &self.suffix[index + 1..])
+      .filter(|extension| !extension.is_empty())
+  }
+}
+
+/// Iterator over the file paths of a `Streak`, see `Streak::paths`
+#[derive(Debug, Clone)]
+pub struct Paths<'a> {
+  streak: &'a Streak,
+  frames: FrameIter<'a>,
+}
+
+impl Iterator for Paths<'_> {
+  type Item = PathBuf;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    self.frames.next().map(|frame| self.streak.path_for(frame))
+  }
+
+  fn size_hint(&self) -> (usize, Option<usize>) {
+    self.frames.size_hint()
+  }
+}
+
+impl DoubleEndedIterator for Paths<'_> {
+  fn next_back(&mut self) -> Option<Self::Item> {
+    self.frames.next_back().map(|frame| self.streak.path_for(frame))
+  }
+}
+
+impl ExactSizeIterator for Paths<'_> {}
+
+impl<'a> IntoIterator for &'a Streak {
+  type Item = PathBuf;
+  type IntoIter = Paths<'a>;
+
+  fn into_iter(self) -> Self::IntoIter {
+    self.paths()
+  }
+}
+
+impl fmt::Display for Streak {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    if self.directory.as_os_str().is_empty() {
+      write!(f, "{}", self.file_name())?;
+    } else {
+      write!(f, "{}", self.directory.join(self.file_name()).display())?;
+    }
+    match self.padding.notation() {
+      Notation::Streaker => Ok(()),
+      _ if self.frames.count() == 0 => Ok(()),
+      _ => write!(f, " {}", self.frames.token()),
+    }
+  }
+}
+
+impl FromStr for Streak {
+  type Err = PatternError;
+
+  /// Parse a sequence such as `/shots/render.1-100#.exr`, `render.#.exr`,
+  /// or a file name template followed by its frames, `render.%04d.exr 1-100`
+  fn from_str(pattern: &str) -> Result<Self, Self::Err> {
+    pattern::parse(pattern)
+  }
+}
+
+impl TryFrom<&str> for Streak {
+  type Error = PatternError;
+
+  fn try_from(pattern: &str) -> Result<Self, Self::Error> {
+    pattern.parse()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+
+  use super::*;
+
+  fn render() -> Streak {
+    Streak::new("render", ".", Padding::new(4), "1-100".parse().unwrap(), ".exr")
+  }
+
+  #[test]
+  fn test_new() {
+    let streak = render();
+    assert_eq!(streak.prefix(), "render");
+    assert_eq!(streak.separator(), ".");
+    assert_eq!(streak.padding(), &Padding::new(4));
+    assert_eq!(streak.frames().count(), 100);
+    assert_eq!(streak.suffix(), ".exr");
+  }
+
+  #[test]
+  fn test_extension() {
+    assert_eq!(render().extension(), Some("exr"));
+    let frames = Frames::new(&[1]);
+    let extension = |suffix| Streak::new("a", ".", Padding::new(1), frames.clone(), suffix)
+      .extension()
+      .map(str::to_owned);
+    assert_eq!(extension(".tar.gz"), Some("tar.gz".to_owned()));
+    assert_eq!(extension("_beauty.exr"), Some("exr".to_owned()));
+    assert_eq!(extension(""), None);
+    assert_eq!(extension("."), None);
+  }
+
+  #[test]
+  fn test_display() {
+    assert_eq!(render().to_string(), "render.1-100#.exr");
+    let streak = Streak::new("plate_", "", Padding::new(3), Frames::new(&[1, 3, 4, 5]), "");
+    assert_eq!(streak.to_string(), "plate_1,3-5@@@");
+  }
+
+  #[test]
+  fn test_directory() {
+    assert_eq!(render().directory(), Path::new(""));
+    let streak = render().with_directory("/shots/a");
+    assert_eq!(streak.directory(), Path::new("/shots/a"));
+    assert_eq!(streak.file_name(), "render.1-100#.exr");
+    assert_eq!(streak.to_string(), "/shots/a/render.1-100#.exr");
+  }
+
+  #[test]
+  fn test_display_notation() {
+    let padding = Padding::new(4).with_notation(Notation::Printf);
+    let mut streak = render().with_directory("/shots/a");
+    streak.set_padding(padding);
+    assert_eq!(streak.file_name(), "render.%04d.exr");
+    assert_eq!(streak.to_string(), "/shots/a/render.%04d.exr 1-100");
+    *streak.frames_mut() = Frames::new(&[]);
+    assert_eq!(streak.to_string(), "/shots/a/render.%04d.exr");
+  }
+
+  #[test]
+  fn test_from_str() {
+    let streak: Streak = "/shots/a/render.1-100#.exr".parse().unwrap();
+    assert_eq!(streak, render().with_directory("/shots/a"));
+    assert_eq!(Streak::try_from("render.exr").map_err(|e| e.position()), Err(0));
+  }
+
+  #[cfg(feature = "serde")]
+  #[test]
+  fn test_serde() {
+    let streak: Streak = "/shots/render.%04d.exr 1-3,5".parse().unwrap();
+    let json = serde_json::to_string(&streak).unwrap();
+    assert_eq!(json, concat!(r#"{"directory":"/shots","prefix":"render","separator":".","#,
+                             r#""padding":{"width":4,"notation":"printf"},"frames":"1-3,5","suffix":".exr","decimals":"fixed"}"#));
+    assert_eq!(serde_json::from_str::<Streak>(&json).unwrap(), streak);
+    let streak: Streak = serde_json::from_str(
+      r#"{"prefix":"a","separator":"_","padding":{"width":2,"notation":"hash"},"frames":[1,2],"suffix":""}"#).unwrap();
+    assert_eq!(streak.to_string(), "a_## 1-2");
+
+    let streak = Streak::new("sim", ".", Padding::new(4), Frames::with_precision(&[100150, 100200], 2), ".bgeo");
+    let json = serde_json::to_string(&streak).unwrap();
+    let restored: Streak = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored.frames().precision(), 2);
+    assert_eq!(restored.paths().collect::<Vec<_>>(), streak.paths().collect::<Vec<_>>());
+    assert_eq!(restored.path_for(100200), PathBuf::from("sim.1002.00.bgeo"));
+  }
+
+  #[test]
+  fn test_paths() {
+    let streak = render().with_directory("/shots/a");
+    assert_eq!(streak.path_for(42), PathBuf::from("/shots/a/render.0042.exr"));
+    assert_eq!(streak.path_for(-1), PathBuf::from("/shots/a/render.-001.exr"));
+    let mut paths = streak.paths();
+    assert_eq!(paths.len(), 100);
+    assert_eq!(paths.next_back(), Some(PathBuf::from("/shots/a/render.0100.exr")));
+    let names: Vec<PathBuf> = Streak::new("a", "_", Padding::new(1), Frames::new(&[9, 10]), "").into_iter().collect();
+    assert_eq!(names, vec![PathBuf::from("a_9"), PathBuf::from("a_10")]);
+  }
+
+  #[test]
+  fn test_paths_subframes() {
+    let frames: Frames = "1-2x0.5".parse().unwrap();
+    let streak = Streak::new("sim", ".", Padding::new(4), frames, ".bgeo");
+    assert_eq!(streak.decimals(), Decimals::Fixed);
+    let names: Vec<PathBuf> = streak.paths().collect();
+    assert_eq!(names, vec![
+      PathBuf::from("sim.0001.0.bgeo"),
+      PathBuf::from("sim.0001.5.bgeo"),
+      PathBuf::from("sim.0002.0.bgeo"),
+    ]);
+    let names: Vec<PathBuf> = streak.with_decimals(Decimals::Trimmed).paths().collect();
+    assert_eq!(names, vec![
+      PathBuf::from("sim.0001.bgeo"),
+      PathBuf::from("sim.0001.5.bgeo"),
+      PathBuf::from("sim.0002.bgeo"),
+    ]);
+  }
+
+  #[test]
+  fn test_frames_mut() {
+    let mut streak = render();
+    *streak.frames_mut() = Frames::new(&[1, 2]);
+    streak.set_padding(Padding::new(1));
+    assert_eq!(streak.to_string(), "render.1-2@.exr");
+  }
+}
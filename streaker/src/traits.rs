@@ -1,3 +1,6 @@
-pub(crate) trait Token {
+/// Types that render to the compact notation used in sequence names
+pub trait Token {
+
+  /// Compact notation, eg. `1-100` for frames or `#` for padding
   fn token(&self) -> String;
-}
\ No newline at end of file
+}
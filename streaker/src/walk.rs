@@ -0,0 +1,332 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use crate::grammar::Grammar;
+use crate::scan::{list, Scan};
+
+/// Recursively scan a directory tree for sequences.
+///
+/// Yields one `Scan` per directory, parents before their children and
+/// siblings ordered by name.
+///
+/// ```no_run
+/// use streaker::Walk;
+///
+/// for scan in Walk::new("/shots").max_depth(4) {
+///   for streak in scan.unwrap().streaks() {
+///     println!("{}", streak);
+///   }
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Walk {
+  max_depth: Option<usize>,
+  follow_links: bool,
+  hidden: bool,
+  grammar: Grammar,
+  pending: Vec<(PathBuf, usize)>,
+  visited: HashSet<PathBuf>,
+}
+
+/// Attach the failing path to an error
+fn annotate(path: &Path, error: io::Error) -> io::Error {
+  io::Error::new(error.kind(), format!("{}: {}", path.display(), error))
+}
+
+impl Walk {
+
+  /// Walk everything below `root`, skipping hidden entries and symlinked directories
+  pub fn new<P: AsRef<Path>>(root: P) -> Self {
+    Walk {
+      max_depth: None,
+      follow_links: false,
+      hidden: false,
+      grammar: Grammar::default(),
+      pending: vec![(root.as_ref().to_path_buf(), 0)],
+      visited: HashSet::new(),
+    }
+  }
+
+  /// Deepest level to descend to, `0` only scans the root
+  pub fn max_depth(mut self, depth: usize) -> Self {
+    self.max_depth = Some(depth);
+    self
+  }
+
+  /// Descend into symlinked directories, each directory is still visited once
+  pub fn follow_links(mut self, follow: bool) -> Self {
+    self.follow_links = follow;
+    self
+  }
+
+  /// Include files and directories starting with a dot
+  pub fn hidden(mut self, hidden: bool) -> Self {
+    self.hidden = hidden;
+    self
+  }
+
+  /// Split file names with `grammar` instead of the default `name.frame.ext`
+  pub fn grammar(mut self, grammar: Grammar) -> Self {
+    self.grammar = grammar;
+    self
+  }
+
+  /// Whether `path` was seen before, through another link
+  fn is_cycle(&mut self, path: &Path) -> io::Result<bool> {
+    if !self.follow_links {
+      return Ok(false);
+    }
+    let canonical = fs::canonicalize(path)?;
+    Ok(!self.visited.insert(canonical))
+  }
+}
+
+#[cfg(feature = "parallel")]
+impl Walk {
+
+  /// Walk the tree with a pool of `threads` workers, `0` picks one per core.
+  ///
+  /// Directories are listed a level at a time so the result is deterministic:
+  /// scans come back in the same order as iterating the walk, and a directory
+  /// reachable through several links is reported under its shallowest,
+  /// then first by name, path.
+  pub fn parallel(mut self, threads: usize) -> Vec<io::Result<Scan>> {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::mpsc;
+    use std::thread;
+
+    let threads = match threads {
+      0 => thread::available_parallelism().map_or(1, |n| n.get()),
+      n => n,
+    };
+    let mut results: Vec<(PathBuf, io::Result<Scan>)> = Vec::new();
+    let mut level: Vec<(PathBuf, usize)> = self.pending.drain(..).collect();
+
+    while !level.is_empty() {
+      let next = AtomicUsize::new(0);
+      let (sender, receiver) = mpsc::channel();
+      let (follow_links, hidden) = (self.follow_links, self.hidden);
+      thread::scope(|scope| {
+        for _ in 0..threads.min(level.len()) {
+          let sender = sender.clone();
+          let (next, level) = (&next, &level);
+          scope.spawn(move || {
+            loop {
+              let index = next.fetch_add(1, Ordering::Relaxed);
+              let Some((path, _)) = level.get(index) else { break };
+              let canonical = if follow_links {
+                fs::canonicalize(path).map(Some)
+              } else {
+                Ok(None)
+              };
+              let listing = canonical.and_then(|canonical| {
+                list(path, follow_links, hidden).map(|listing| (canonical, listing))
+              });
+              let _ = sender.send((index, listing));
+            }
+          });
+        }
+      });
+      drop(sender);
+
+      let mut listings: Vec<_> = receiver.into_iter().collect();
+      listings.sort_by_key(|(index, _)| *index);
+      let mut children = Vec::new();
+      for (index, listing) in listings {
+        let (path, depth) = &level[index];
+        let (canonical, listing) = match listing {
+          Ok(listing) => listing,
+          Err(error) => {
+            results.push((path.clone(), Err(annotate(path, error))));
+            continue;
+          }
+        };
+        if let Some(canonical) = canonical {
+          if !self.visited.insert(canonical) {
+            continue;
+          }
+        }
+        if self.max_depth.is_none_or(|max| *depth < max) {
+          children.extend(listing.directories.into_iter().map(|directory| (directory, depth + 1)));
+        }
+        results.push((path.clone(), Ok(Scan::from_names(path, listing.files, &self.grammar))));
+      }
+      level = children;
+    }
+
+    results.sort_by(|(a, _), (b, _)| a.cmp(b));
+    results.into_iter().map(|(_, result)| result).collect()
+  }
+}
+
+impl Iterator for Walk {
+  type Item = io::Result<Scan>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    while let Some((path, depth)) = self.pending.pop() {
+      match self.is_cycle(&path) {
+        Ok(true) => continue,
+        Ok(false) => {}
+        Err(error) => return Some(Err(annotate(&path, error))),
+      }
+      let listing = match list(&path, self.follow_links, self.hidden) {
+        Ok(listing) => listing,
+        Err(error) => return Some(Err(annotate(&path, error))),
+      };
+      if self.max_depth.is_none_or(|max| depth < max) {
+        for directory in listing.directories.into_iter().rev() {
+          self.pending.push((directory, depth + 1));
+        }
+      }
+      return Some(Ok(Scan::from_names(&path, listing.files, &self.grammar)));
+    }
+    None
+  }
+}
+
+#[cfg(test)]
+mod tests {
+
+  use super::*;
+  use crate::fixture::Fixture;
+  use crate::grammar::Rule;
+
+  fn tree() -> Vec<&'static str> {
+    vec![
+      "top.0001.exr",
+      "seq/shot/render/beauty/v001/beauty.1001.exr",
+      "seq/shot/render/beauty/v001/beauty.1002.exr",
+      "seq/shot/render/beauty/v002/beauty.1001.exr",
+      "seq/shot/plate/plate.0001.dpx",
+      "seq/.cache/tmp.0001.exr",
+      "seq/.hidden.0001.exr",
+    ]
+  }
+
+  fn walk(walk: Walk, root: &Path) -> Vec<String> {
+    walk
+      .map(|scan| scan.unwrap())
+      .flat_map(|scan| scan.streaks().to_vec())
+      .map(|streak| streak.to_string()[root.as_os_str().len() + 1..].to_owned())
+      .collect()
+  }
+
+  #[test]
+  fn test_walk() {
+    let fixture = Fixture::new("walk", &tree());
+    let found = walk(Walk::new(fixture.path()), fixture.path());
+    assert_eq!(found, vec![
+      "top.1#.exr",
+      "seq/shot/plate/plate.1#.dpx",
+      "seq/shot/render/beauty/v001/beauty.1001-1002#.exr",
+      "seq/shot/render/beauty/v002/beauty.1001#.exr",
+    ]);
+  }
+
+  #[test]
+  fn test_walk_directories() {
+    let fixture = Fixture::new("walk-directories", &tree());
+    let directories: Vec<PathBuf> = Walk::new(fixture.path())
+      .map(|scan| scan.unwrap().directory().strip_prefix(fixture.path()).unwrap().to_path_buf())
+      .collect();
+    assert_eq!(directories, vec![
+      PathBuf::from(""),
+      PathBuf::from("seq"),
+      PathBuf::from("seq/shot"),
+      PathBuf::from("seq/shot/plate"),
+      PathBuf::from("seq/shot/render"),
+      PathBuf::from("seq/shot/render/beauty"),
+      PathBuf::from("seq/shot/render/beauty/v001"),
+      PathBuf::from("seq/shot/render/beauty/v002"),
+    ]);
+  }
+
+  #[test]
+  fn test_walk_max_depth() {
+    let fixture = Fixture::new("walk-max-depth", &tree());
+    assert_eq!(walk(Walk::new(fixture.path()).max_depth(0), fixture.path()), vec!["top.1#.exr"]);
+    assert_eq!(walk(Walk::new(fixture.path()).max_depth(3), fixture.path()), vec![
+      "top.1#.exr",
+      "seq/shot/plate/plate.1#.dpx",
+    ]);
+  }
+
+  #[test]
+  fn test_walk_hidden() {
+    let fixture = Fixture::new("walk-hidden", &tree());
+    let found = walk(Walk::new(fixture.path()).hidden(true).max_depth(2), fixture.path());
+    assert_eq!(found, vec![
+      "top.1#.exr",
+      "seq/.hidden.1#.exr",
+      "seq/.cache/tmp.1#.exr",
+    ]);
+  }
+
+  #[test]
+  fn test_walk_grammar() {
+    let fixture = Fixture::new("walk-grammar", &["a/render_1001.exr", "a/render_1002.exr"]);
+    assert!(walk(Walk::new(fixture.path()), fixture.path()).is_empty());
+    let grammar = Grammar::new(Rule::BeforeExtension);
+    assert_eq!(walk(Walk::new(fixture.path()).grammar(grammar), fixture.path()),
+               vec!["a/render_1001-1002#.exr"]);
+  }
+
+  #[test]
+  fn test_walk_missing() {
+    let fixture = Fixture::new("walk-missing", &[]);
+    let mut walk = Walk::new(fixture.path().join("missing"));
+    assert!(walk.next().unwrap().is_err());
+    assert!(walk.next().is_none());
+  }
+
+  #[cfg(feature = "parallel")]
+  #[test]
+  fn test_walk_parallel() {
+    let fixture = Fixture::new("walk-parallel", &tree());
+    let directory = |scan: &io::Result<Scan>| scan.as_ref().unwrap().directory().to_path_buf();
+    let serial: Vec<Scan> = Walk::new(fixture.path()).map(Result::unwrap).collect();
+    for threads in 0..4 {
+      let parallel = Walk::new(fixture.path()).hidden(true).max_depth(3).parallel(threads);
+      let expected: Vec<PathBuf> = Walk::new(fixture.path()).hidden(true).max_depth(3)
+        .map(|scan| directory(&scan))
+        .collect();
+      assert_eq!(parallel.iter().map(directory).collect::<Vec<_>>(), expected);
+      let parallel: Vec<Scan> = Walk::new(fixture.path()).parallel(threads)
+        .into_iter()
+        .map(Result::unwrap)
+        .collect();
+      assert_eq!(parallel, serial);
+    }
+    let missing = Walk::new(fixture.path().join("missing")).parallel(2);
+    assert_eq!(missing.len(), 1);
+    assert!(missing[0].is_err());
+  }
+
+  #[cfg(all(unix, feature = "parallel"))]
+  #[test]
+  fn test_walk_parallel_links() {
+    use std::os::unix::fs::symlink;
+    let fixture = Fixture::new("walk-parallel-links", &["a/render.0001.exr"]);
+    symlink(fixture.path(), fixture.path().join("a/loop")).unwrap();
+    let scans = Walk::new(fixture.path()).follow_links(true).parallel(2);
+    assert_eq!(scans.len(), 2);
+  }
+
+  #[cfg(unix)]
+  #[test]
+  fn test_walk_links() {
+    use std::os::unix::fs::symlink;
+    let fixture = Fixture::new("walk-links", &["a/render.0001.exr"]);
+    let target = Fixture::new("walk-links-target", &["plate.0001.dpx"]);
+    symlink(fixture.path().join("a"), fixture.path().join("b")).unwrap();
+    symlink(target.path(), fixture.path().join("c")).unwrap();
+    symlink(fixture.path(), fixture.path().join("a/loop")).unwrap();
+
+    let skipped = walk(Walk::new(fixture.path()), fixture.path());
+    assert_eq!(skipped, vec!["a/render.1#.exr"]);
+
+    let followed = walk(Walk::new(fixture.path()).follow_links(true), fixture.path());
+    assert_eq!(followed, vec!["a/render.1#.exr", "c/plate.1#.dpx"]);
+  }
+}